@@ -1,13 +1,31 @@
+use crate::save_sync;
 use crate::types::{AppState, AppStateEvent, TrackedApp};
+use crate::util::now_ms;
 use std::collections::HashMap;
 use std::sync::{LazyLock, Mutex};
-use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
 use tauri::{AppHandle, Emitter};
 use tokio::time::{sleep, Duration};
 
+const FORTNITE_PROCESS_NAME: &str = "FortniteClient-Win64-Shipping.exe";
+/// How long a launch counts as running while its launcher is gone and the game has not
+/// shown up yet.
+const GAME_START_GRACE_MS: u64 = 30_000;
+
 static TRACKED_APPS: LazyLock<Mutex<HashMap<u32, TrackedApp>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
+/// Apps started by `launch_app`, keyed by the pid of the launched process. That may be a
+/// runner, wrapper or launcher, so the game itself is found by its executable name.
+static LAUNCHES: LazyLock<Mutex<HashMap<u32, Launch>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct Launch {
+    executable: String,
+    started_at: u64,
+    game_seen: bool,
+}
+
 pub fn get_tracked_apps() -> Result<Vec<TrackedApp>, String> {
     let apps = TRACKED_APPS
         .lock()
@@ -16,13 +34,22 @@ pub fn get_tracked_apps() -> Result<Vec<TrackedApp>, String> {
     Ok(apps.values().cloned().collect())
 }
 
-pub fn track_app(pid: u32, app_id: &str) {
+pub fn track_app(pid: u32, app_id: &str, executable: &str) {
     let app = TrackedApp {
         pid,
         app_id: app_id.to_string(),
         is_running: true,
     };
 
+    LAUNCHES.lock().unwrap().insert(
+        pid,
+        Launch {
+            executable: executable.to_string(),
+            started_at: now_ms(),
+            game_seen: false,
+        },
+    );
+
     let mut apps = TRACKED_APPS.lock().unwrap();
     apps.insert(pid, app);
 }
@@ -43,16 +70,23 @@ pub fn stop_app(app_id: &str) -> Result<bool, String> {
         let pid = tracked_app.pid;
         let mut system = System::new();
 
-        system.refresh_processes_specifics(
-            ProcessesToUpdate::Some(&[Pid::from_u32(pid)]),
-            true,
-            ProcessRefreshKind::nothing(),
-        );
+        system.refresh_processes_specifics(ProcessesToUpdate::All, true, monitor_refresh_kind());
 
         if let Some(process) = system.process(Pid::from_u32(pid)) {
             process.kill();
         }
 
+        // The game may have outlived the process that launched it
+        if let Some(launch) = LAUNCHES.lock().unwrap().get(&pid) {
+            system
+                .processes()
+                .values()
+                .filter(|process| is_process_of(process, &launch.executable))
+                .for_each(|process| {
+                    process.kill();
+                });
+        }
+
         Ok(true)
     } else {
         Err(format!("App with ID '{}' not found", app_id))
@@ -69,19 +103,24 @@ pub fn start_monitoring(app: AppHandle) {
             system.refresh_processes_specifics(
                 ProcessesToUpdate::All,
                 true,
-                ProcessRefreshKind::nothing(),
+                monitor_refresh_kind(),
             );
 
             let fortnite_process = system
                 .processes()
                 .values()
-                .find(|process| process.name() == "FortniteClient-Win64-Shipping.exe");
+                .find(|process| is_fortnite_process(process));
 
             if let Some(fn_process) = fortnite_process {
                 let fn_pid = fn_process.pid().as_u32();
 
+                // A launch of Fortnite is already tracked through its launched process
                 let mut apps = TRACKED_APPS.lock().unwrap();
-                if !apps.contains_key(&fn_pid) {
+                let is_tracked = apps
+                    .values()
+                    .any(|tracked| tracked.pid == fn_pid || tracked.app_id == "Fortnite");
+
+                if !is_tracked {
                     apps.insert(
                         fn_pid,
                         TrackedApp {
@@ -107,7 +146,8 @@ pub fn start_monitoring(app: AppHandle) {
             let mut pids_to_remove = Vec::new();
 
             for pid in tracked_pids {
-                let process_exists = system.process(Pid::from_u32(pid)).is_some();
+                let process_exists =
+                    is_launch_running(&system, pid, system.process(Pid::from_u32(pid)).is_some());
 
                 if process_exists {
                     let mut apps = TRACKED_APPS.lock().unwrap();
@@ -134,6 +174,7 @@ pub fn start_monitoring(app: AppHandle) {
                                     &tracked_app.app_id,
                                     AppState::Stopped,
                                 );
+                                save_sync::sync_after_exit(&app, &tracked_app.app_id);
                                 true
                             } else {
                                 false
@@ -153,11 +194,55 @@ pub fn start_monitoring(app: AppHandle) {
                 tauri::async_runtime::spawn(async move {
                     sleep(Duration::from_secs(1)).await;
                     let mut apps = TRACKED_APPS.lock().unwrap();
+                    let mut launches = LAUNCHES.lock().unwrap();
                     for pid in pids_to_remove {
                         apps.remove(&pid);
+                        launches.remove(&pid);
                     }
                 });
             }
         }
     });
 }
+
+fn monitor_refresh_kind() -> ProcessRefreshKind {
+    if cfg!(windows) {
+        ProcessRefreshKind::nothing()
+    } else {
+        ProcessRefreshKind::nothing().with_cmd(UpdateKind::OnlyIfNotSet)
+    }
+}
+
+/// A launch runs as long as the launched process or the game does. The launched process
+/// may exit before the game starts, which gets `GAME_START_GRACE_MS` to show up.
+fn is_launch_running(system: &System, pid: u32, process_exists: bool) -> bool {
+    let mut launches = LAUNCHES.lock().unwrap();
+    let Some(launch) = launches.get_mut(&pid) else {
+        return process_exists;
+    };
+
+    let game_exists = system
+        .processes()
+        .values()
+        .any(|process| is_process_of(process, &launch.executable));
+    launch.game_seen |= game_exists;
+
+    process_exists
+        || game_exists
+        || (!launch.game_seen && now_ms().saturating_sub(launch.started_at) < GAME_START_GRACE_MS)
+}
+
+fn is_fortnite_process(process: &Process) -> bool {
+    is_process_of(process, FORTNITE_PROCESS_NAME)
+}
+
+/// Wine and Proton processes only expose the first 15 characters of the
+/// executable name on Linux, so the command line is checked as well.
+fn is_process_of(process: &Process, executable: &str) -> bool {
+    !executable.is_empty()
+        && (process.name() == executable
+            || process
+                .cmd()
+                .first()
+                .is_some_and(|arg| arg.to_string_lossy().ends_with(executable)))
+}
@@ -1,9 +1,23 @@
-#[cfg(windows)]
+#[cfg(desktop)]
 use {
     crate::app_monitor,
+    crate::download_queue,
+    crate::install,
+    crate::install_mover,
     crate::legendary,
-    crate::types::{AppState, CommandOutput, DiskSpace, LaunchData, TrackedApp},
+    crate::logs,
+    crate::runner,
+    crate::save_sync,
+    crate::types::{
+        AppState, CommandOutput, DiskSpace, DlcInfo, DlcResult, DownloadOptions,
+        DownloadQueueState, ImportResult, InstallTagInfo, LaunchData, LegendaryAppInfo,
+        LegendaryError, LegendaryGame, LegendaryInstalledApp, LegendaryLogInfo, LegendaryOperation,
+        LegendaryStatus, MoveResult, PreflightReport, QueueDirection, QueuedApp, RunOptions,
+        RunnerConfig, SaveSyncDirection, SaveSyncResult, StreamEvent, StreamInfo, TrackedApp,
+        Volume,
+    },
     fs2, shlex,
+    std::collections::HashMap,
     std::path::Path,
     tauri::AppHandle,
     tauri_plugin_shell::ShellExt,
@@ -18,7 +32,7 @@ pub fn get_locale() -> String {
         .unwrap_or_else(|| "".to_string())
 }
 
-#[cfg(windows)]
+#[cfg(desktop)]
 #[command]
 pub fn get_disk_space(dir: String) -> Result<DiskSpace, String> {
     let path = Path::new(&dir);
@@ -33,13 +47,120 @@ pub fn get_disk_space(dir: String) -> Result<DiskSpace, String> {
     }
 }
 
-#[cfg(windows)]
+#[cfg(desktop)]
+#[command]
+pub async fn import_installation(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+    path: String,
+    stream_id: String,
+) -> Result<ImportResult, LegendaryError> {
+    install::import_installation(&app, &config_path, &app_id, &path, &stream_id).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn move_installation(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+    new_base_path: String,
+) -> Result<MoveResult, LegendaryError> {
+    install_mover::move_installation(&app, &config_path, &app_id, &new_base_path).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn cancel_move_installation(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+) -> Result<bool, LegendaryError> {
+    install_mover::cancel_move(&app, &config_path, &app_id).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn get_pending_moves(app: AppHandle) -> Result<HashMap<String, String>, String> {
+    install_mover::get_pending_moves(&app)
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn list_volumes() -> Vec<Volume> {
+    install::list_volumes()
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn preflight_install(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+    base_path: String,
+    dlc_ids: Option<Vec<String>>,
+) -> Result<PreflightReport, LegendaryError> {
+    install::preflight_install(
+        &app,
+        &config_path,
+        &app_id,
+        &base_path,
+        &dlc_ids.unwrap_or_default(),
+    )
+    .await
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn list_install_tags(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+) -> Result<InstallTagInfo, LegendaryError> {
+    install::list_install_tags(&app, &config_path, &app_id).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn list_dlc(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+) -> Result<Vec<DlcInfo>, LegendaryError> {
+    install::list_dlc(&app, &config_path, &app_id).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn install_dlc(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+    dlc_ids: Vec<String>,
+    stream_id: String,
+) -> Result<Vec<DlcResult>, LegendaryError> {
+    install::install_dlc(&app, &config_path, &app_id, &dlc_ids, &stream_id).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn uninstall_dlc(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+    dlc_ids: Vec<String>,
+) -> Result<Vec<DlcResult>, LegendaryError> {
+    install::uninstall_dlc(&app, &config_path, &app_id, &dlc_ids).await
+}
+
+#[cfg(desktop)]
 #[command]
 pub fn get_tracked_apps() -> Result<Vec<TrackedApp>, String> {
     app_monitor::get_tracked_apps()
 }
 
-#[cfg(windows)]
+#[cfg(desktop)]
 #[command]
 pub async fn launch_app(app: AppHandle, launch_data: LaunchData) -> Result<u32, String> {
     if !launch_data.pre_launch_command.is_empty() {
@@ -49,51 +170,287 @@ pub async fn launch_app(app: AppHandle, launch_data: LaunchData) -> Result<u32,
         }
     }
 
-    let pid = launch_application(&app, &launch_data).await?;
+    save_sync::sync_before_launch(&app, &launch_data.game_id).await;
+
+    let runner = match &launch_data.runner {
+        Some(runner) => runner.clone(),
+        None => runner::get_app_runner(&app, &launch_data.game_id)?,
+    };
+
+    let pid = launch_application(&app, &launch_data, &runner).await?;
+
+    let executable = Path::new(&launch_data.game_executable)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| launch_data.game_executable.clone());
 
-    app_monitor::track_app(pid, &launch_data.game_id);
+    app_monitor::track_app(pid, &launch_data.game_id, &executable);
     app_monitor::emit_app_state_changed(&app, pid, &launch_data.game_id, AppState::Running);
 
     Ok(pid)
 }
 
-#[cfg(windows)]
+#[cfg(desktop)]
 #[command]
 pub async fn stop_app(_app: AppHandle, app_id: String) -> Result<bool, String> {
     app_monitor::stop_app(&app_id)
 }
 
-#[cfg(windows)]
+#[cfg(desktop)]
+#[command]
+pub async fn sync_saves(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+    direction: Option<SaveSyncDirection>,
+    force: Option<bool>,
+) -> Result<SaveSyncResult, LegendaryError> {
+    save_sync::sync_saves(
+        &app,
+        &config_path,
+        &app_id,
+        direction.unwrap_or_default(),
+        force.unwrap_or_default(),
+    )
+    .await
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn get_auto_save_sync(app: AppHandle, app_id: String) -> Result<bool, String> {
+    save_sync::get_auto_sync(&app, &app_id)
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn set_auto_save_sync(app: AppHandle, app_id: String, enabled: bool) -> Result<(), String> {
+    save_sync::set_auto_sync(&app, &app_id, enabled)
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn get_app_runner(app: AppHandle, app_id: String) -> Result<RunnerConfig, String> {
+    runner::get_app_runner(&app, &app_id)
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn set_app_runner(app: AppHandle, app_id: String, runner: RunnerConfig) -> Result<(), String> {
+    runner::set_app_runner(&app, &app_id, runner)
+}
+
+#[cfg(desktop)]
 #[command]
 pub async fn run_legendary(
     app: AppHandle,
     config_path: String,
-    args: Vec<String>,
-) -> Result<CommandOutput, String> {
-    legendary::run_legendary(&app, &config_path, &args).await
+    operation: LegendaryOperation,
+    options: Option<RunOptions>,
+) -> Result<CommandOutput, LegendaryError> {
+    legendary::run_legendary(&app, &config_path, &operation, &options.unwrap_or_default()).await
 }
 
-#[cfg(windows)]
+#[cfg(desktop)]
+#[command]
+pub fn cancel_legendary(request_id: String) -> bool {
+    legendary::cancel_legendary(&request_id)
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn legendary_list(
+    app: AppHandle,
+    config_path: String,
+    options: Option<RunOptions>,
+) -> Result<Vec<LegendaryGame>, LegendaryError> {
+    legendary::get_list(&app, &config_path, &options.unwrap_or_default()).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn legendary_status(
+    app: AppHandle,
+    config_path: String,
+    options: Option<RunOptions>,
+) -> Result<LegendaryStatus, LegendaryError> {
+    legendary::get_status(&app, &config_path, &options.unwrap_or_default()).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn legendary_info(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+    options: Option<RunOptions>,
+) -> Result<LegendaryAppInfo, LegendaryError> {
+    legendary::get_app_info(&app, &config_path, &app_id, &options.unwrap_or_default()).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn legendary_installed(
+    app: AppHandle,
+    config_path: String,
+    options: Option<RunOptions>,
+) -> Result<Vec<LegendaryInstalledApp>, LegendaryError> {
+    legendary::get_installed_list(&app, &config_path, &options.unwrap_or_default()).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn legendary_launch_data(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+    options: Option<RunOptions>,
+) -> Result<LaunchData, LegendaryError> {
+    legendary::get_launch_data(&app, &config_path, &app_id, &options.unwrap_or_default()).await
+}
+
+#[cfg(desktop)]
 #[command]
 pub async fn start_legendary_stream(
     app: AppHandle,
     config_path: String,
     stream_id: String,
-    args: Vec<String>,
+    operation: LegendaryOperation,
+    download_size: Option<u64>,
+    log: Option<bool>,
 ) -> Result<String, String> {
-    legendary::start_legendary_stream(&app, &config_path, &stream_id, &args).await
+    legendary::start_legendary_stream(
+        &app,
+        &config_path,
+        &stream_id,
+        &operation,
+        download_size,
+        log.unwrap_or_default(),
+    )
+    .await
 }
 
-#[cfg(windows)]
+#[cfg(desktop)]
 #[command]
 pub async fn stop_legendary_stream(
     stream_id: String,
     force_kill_all: bool,
+    grace_period_ms: Option<u64>,
 ) -> Result<bool, String> {
-    legendary::stop_legendary_stream(&stream_id, force_kill_all).await
+    legendary::stop_legendary_stream(&stream_id, force_kill_all, grace_period_ms).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn write_legendary_stream(stream_id: String, data: String) -> Result<(), String> {
+    legendary::write_stream(&stream_id, &data)
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn list_legendary_streams() -> Vec<StreamInfo> {
+    legendary::list_streams()
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn get_legendary_stream_log(
+    stream_id: String,
+    since_seq: Option<u64>,
+) -> Result<Vec<StreamEvent>, String> {
+    legendary::get_stream_log(&stream_id, since_seq)
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn list_legendary_logs(
+    app: AppHandle,
+    app_id: Option<String>,
+    operation: Option<String>,
+) -> Result<Vec<LegendaryLogInfo>, String> {
+    logs::list_logs(&app, app_id.as_deref(), operation.as_deref())
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn read_legendary_log(app: AppHandle, file_name: String) -> Result<String, String> {
+    logs::read_log(&app, &file_name)
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn get_download_queue(
+    app: AppHandle,
+    config_path: String,
+) -> Result<DownloadQueueState, String> {
+    download_queue::get_queue(&app, &config_path)
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn enqueue_download(
+    app: AppHandle,
+    config_path: String,
+    item: QueuedApp,
+    options: DownloadOptions,
+) -> Result<DownloadQueueState, String> {
+    download_queue::enqueue(&app, &config_path, item, options)
+}
+
+#[cfg(desktop)]
+#[command]
+pub async fn dequeue_download(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+) -> Result<DownloadQueueState, String> {
+    download_queue::dequeue(&app, &config_path, &app_id).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn move_download(
+    app: AppHandle,
+    config_path: String,
+    app_id: String,
+    direction: QueueDirection,
+) -> Result<DownloadQueueState, String> {
+    download_queue::move_item(&app, &config_path, &app_id, direction)
 }
 
-#[cfg(windows)]
+#[cfg(desktop)]
+#[command]
+pub async fn pause_download(app: AppHandle) -> Result<DownloadQueueState, String> {
+    download_queue::pause(&app).await
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn resume_download(app: AppHandle) -> Result<DownloadQueueState, String> {
+    download_queue::resume(&app)
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn get_disk_space_threshold() -> Result<u64, String> {
+    download_queue::get_disk_space_threshold()
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn set_disk_space_threshold(app: AppHandle, threshold: u64) -> Result<(), String> {
+    download_queue::set_disk_space_threshold(&app, threshold)
+}
+
+#[cfg(desktop)]
+#[command]
+pub fn clear_finished_downloads(
+    app: AppHandle,
+    config_path: String,
+) -> Result<DownloadQueueState, String> {
+    download_queue::clear_finished(&app, &config_path)
+}
+
+#[cfg(desktop)]
 async fn execute_pre_launch_command(
     app: &AppHandle,
     launch_data: &LaunchData,
@@ -153,8 +510,12 @@ async fn execute_pre_launch_command(
     Ok(())
 }
 
-#[cfg(windows)]
-async fn launch_application(app: &AppHandle, launch_data: &LaunchData) -> Result<u32, String> {
+#[cfg(desktop)]
+async fn launch_application(
+    app: &AppHandle,
+    launch_data: &LaunchData,
+    runner: &RunnerConfig,
+) -> Result<u32, String> {
     let shell = app.shell();
 
     let executable_path = Path::new(&launch_data.game_directory).join(&launch_data.game_executable);
@@ -166,11 +527,17 @@ async fn launch_application(app: &AppHandle, launch_data: &LaunchData) -> Result
         ));
     }
 
-    let mut command = shell
-        .command(executable_path.to_string_lossy().to_string())
-        .args(&launch_data.game_parameters)
-        .args(&launch_data.user_parameters)
-        .args(&launch_data.egl_parameters);
+    let args = [
+        launch_data.game_parameters.as_slice(),
+        launch_data.user_parameters.as_slice(),
+        launch_data.egl_parameters.as_slice(),
+    ]
+    .concat();
+
+    let invocation =
+        runner::wrap_command(runner, &launch_data.launch_command, &executable_path, args)?;
+
+    let mut command = shell.command(invocation.program).args(invocation.args);
 
     if !launch_data.working_directory.is_empty()
         && Path::new(&launch_data.working_directory).exists()
@@ -178,7 +545,11 @@ async fn launch_application(app: &AppHandle, launch_data: &LaunchData) -> Result
         command = command.current_dir(&launch_data.working_directory);
     }
 
-    for (key, value) in &launch_data.environment {
+    for (key, value) in launch_data
+        .environment
+        .iter()
+        .chain(&invocation.environment)
+    {
         command = command.env(key, value);
     }
 
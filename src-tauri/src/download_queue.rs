@@ -0,0 +1,750 @@
+use crate::commands::get_disk_space;
+use crate::install;
+use crate::legendary;
+use crate::storage;
+use crate::types::{
+    DiskSpaceEvent, DownloadFinishedEvent, DownloadOperation, DownloadOptions, DownloadProgress,
+    DownloadQueueState, EventType, InstallOptions, LegendaryFailure, LegendaryOperation,
+    QueueDirection, QueueItem, QueueItemStatus, QueuedApp, StreamEvent,
+};
+use crate::util::now_ms;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+const QUEUE_FILE: &str = "download-queue.json";
+const DEFAULT_DISK_SPACE_THRESHOLD: u64 = 2 * 1024 * 1024 * 1024;
+const DISK_SPACE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize)]
+struct PersistedQueue {
+    config_path: Option<String>,
+    queues: HashMap<String, Vec<QueueItem>>,
+    #[serde(default = "default_disk_space_threshold")]
+    disk_space_threshold: u64,
+}
+
+impl Default for PersistedQueue {
+    fn default() -> Self {
+        Self {
+            config_path: None,
+            queues: HashMap::new(),
+            disk_space_threshold: DEFAULT_DISK_SPACE_THRESHOLD,
+        }
+    }
+}
+
+fn default_disk_space_threshold() -> u64 {
+    DEFAULT_DISK_SPACE_THRESHOLD
+}
+
+struct ActiveDownload {
+    app_id: String,
+    stream_id: String,
+}
+
+/// A download the disk space watcher paused, `recovered` is set once the offer to resume
+/// has been sent so it is only sent once. `remaining` is what it still had to write.
+struct LowDiskSpacePause {
+    app_id: String,
+    remaining: u64,
+    recovered: bool,
+}
+
+#[derive(Default)]
+struct DownloadQueue {
+    config_path: Option<String>,
+    account_id: Option<String>,
+    items: Vec<QueueItem>,
+    active: Option<ActiveDownload>,
+    progress: Option<DownloadProgress>,
+    disk_space_threshold: u64,
+    low_disk_space: Option<LowDiskSpacePause>,
+}
+
+static DOWNLOAD_QUEUE: LazyLock<Mutex<DownloadQueue>> =
+    LazyLock::new(|| Mutex::new(DownloadQueue::default()));
+
+/// Loads the saved queue and resumes the download that was running when the app closed.
+/// Downloads the user paused stay paused.
+pub fn start(app: AppHandle) {
+    let resume = || -> Result<(), String> {
+        let persisted: PersistedQueue = storage::read_json(&app, QUEUE_FILE)?;
+        let config_path = match persisted.config_path {
+            Some(config_path) => config_path,
+            None => legendary::default_config_path(&app)?,
+        };
+
+        let interrupted = {
+            let mut queue = lock_queue()?;
+            queue.disk_space_threshold = persisted.disk_space_threshold;
+            sync_account(&app, &mut queue, &config_path)?
+        };
+
+        process_queue(&app, interrupted)
+    };
+
+    let _ = resume();
+
+    tauri::async_runtime::spawn(watch_disk_space(app));
+}
+
+pub fn get_queue(app: &AppHandle, config_path: &str) -> Result<DownloadQueueState, String> {
+    let mut queue = lock_queue()?;
+    sync_account(app, &mut queue, config_path)?;
+
+    Ok(queue.state())
+}
+
+pub fn enqueue(
+    app: &AppHandle,
+    config_path: &str,
+    item: QueuedApp,
+    mut options: DownloadOptions,
+) -> Result<DownloadQueueState, String> {
+    {
+        let mut queue = lock_queue()?;
+        sync_account(app, &mut queue, config_path)?;
+
+        let Some(account_id) = legendary::get_account_id(config_path) else {
+            return Err("Legendary is not logged in".to_string());
+        };
+
+        // The queue only switches accounts once the running download of the previous one ends
+        let mut persisted: Option<PersistedQueue> = None;
+        if queue.account_id.as_ref() != Some(&account_id) {
+            persisted = Some(storage::read_json(app, QUEUE_FILE)?);
+        }
+
+        let items = match &mut persisted {
+            Some(persisted) => persisted.queues.entry(account_id).or_default(),
+            None => &mut queue.items,
+        };
+
+        if items
+            .iter()
+            .any(|queued| queued.item.id == item.id && is_pending(queued.status))
+        {
+            return Err("App is already in the download queue".to_string());
+        }
+
+        let operation = if item.requires_repair.unwrap_or_default() {
+            DownloadOperation::Repair
+        } else if item.has_update.unwrap_or_default() {
+            DownloadOperation::Update
+        } else {
+            DownloadOperation::Install
+        };
+
+        // The chosen tags are reused by later updates and repairs of the app
+        match &options.install_tags {
+            Some(install_tags) => install::save_install_tags(app, &item.id, install_tags)?,
+            None => options.install_tags = install::get_saved_install_tags(app, &item.id)?,
+        }
+
+        // Completed or failed entries for the same app are replaced
+        items.retain(|queued| queued.item.id != item.id);
+        items.push(QueueItem {
+            status: QueueItemStatus::Queued,
+            item,
+            operation,
+            options,
+            added_at: now_ms(),
+            started_at: None,
+            completed_at: None,
+            total_download_size: None,
+        });
+
+        match &persisted {
+            Some(persisted) => storage::write_json(app, QUEUE_FILE, persisted)?,
+            None => queue.commit(app)?,
+        }
+    }
+
+    process_queue(app, false)?;
+
+    Ok(lock_queue()?.state())
+}
+
+pub async fn dequeue(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+) -> Result<DownloadQueueState, String> {
+    let active_stream = {
+        let mut queue = lock_queue()?;
+        sync_account(app, &mut queue, config_path)?;
+
+        let was_paused = queue
+            .items
+            .iter()
+            .any(|queued| queued.item.id == app_id && queued.status == QueueItemStatus::Paused);
+
+        queue.items.retain(|queued| queued.item.id != app_id);
+
+        if was_paused {
+            queue.progress = None;
+        }
+
+        queue.commit(app)?;
+
+        queue
+            .active
+            .as_ref()
+            .filter(|active| active.app_id == app_id)
+            .map(|active| active.stream_id.clone())
+    };
+
+    match active_stream {
+        // The download task notices the missing item once the stream terminates
+        Some(stream_id) => {
+            legendary::stop_legendary_stream(&stream_id, false, Some(0)).await?;
+        }
+        None => process_queue(app, false)?,
+    }
+
+    Ok(lock_queue()?.state())
+}
+
+/// Swaps the item with its neighbour among the items that are still waiting.
+pub fn move_item(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    direction: QueueDirection,
+) -> Result<DownloadQueueState, String> {
+    let mut queue = lock_queue()?;
+    sync_account(app, &mut queue, config_path)?;
+
+    let queued_indexes: Vec<usize> = queue
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(_, queued)| queued.status == QueueItemStatus::Queued)
+        .map(|(index, _)| index)
+        .collect();
+
+    let Some(position) = queued_indexes
+        .iter()
+        .position(|index| queue.items[*index].item.id == app_id)
+    else {
+        return Ok(queue.state());
+    };
+
+    let target = match direction {
+        QueueDirection::Up => position.checked_sub(1),
+        QueueDirection::Down => Some(position + 1).filter(|target| *target < queued_indexes.len()),
+    };
+
+    if let Some(target) = target {
+        queue
+            .items
+            .swap(queued_indexes[position], queued_indexes[target]);
+        queue.commit(app)?;
+    }
+
+    Ok(queue.state())
+}
+
+pub async fn pause(app: &AppHandle) -> Result<DownloadQueueState, String> {
+    let stream_id = {
+        let mut queue = lock_queue()?;
+
+        let Some(active) = &queue.active else {
+            return Ok(queue.state());
+        };
+
+        let app_id = active.app_id.clone();
+        let stream_id = active.stream_id.clone();
+
+        if let Some(item) = queue.item_mut(&app_id) {
+            item.status = QueueItemStatus::Paused;
+        }
+
+        queue.commit(app)?;
+
+        stream_id
+    };
+
+    legendary::stop_legendary_stream(&stream_id, false, None).await?;
+
+    Ok(lock_queue()?.state())
+}
+
+pub fn resume(app: &AppHandle) -> Result<DownloadQueueState, String> {
+    process_queue(app, true)?;
+
+    Ok(lock_queue()?.state())
+}
+
+/// Whether the queue is installing, updating or repairing the app right now.
+pub fn is_downloading(app_id: &str) -> Result<bool, String> {
+    Ok(lock_queue()?
+        .active
+        .as_ref()
+        .is_some_and(|active| active.app_id == app_id))
+}
+
+pub fn get_disk_space_threshold() -> Result<u64, String> {
+    Ok(lock_queue()?.disk_space_threshold)
+}
+
+pub fn set_disk_space_threshold(app: &AppHandle, threshold: u64) -> Result<(), String> {
+    let mut queue = lock_queue()?;
+
+    let mut persisted: PersistedQueue = storage::read_json(app, QUEUE_FILE)?;
+    persisted.disk_space_threshold = threshold;
+    storage::write_json(app, QUEUE_FILE, &persisted)?;
+
+    queue.disk_space_threshold = threshold;
+
+    Ok(())
+}
+
+pub fn clear_finished(app: &AppHandle, config_path: &str) -> Result<DownloadQueueState, String> {
+    let mut queue = lock_queue()?;
+    sync_account(app, &mut queue, config_path)?;
+
+    queue.items.retain(|queued| is_pending(queued.status));
+    queue.commit(app)?;
+
+    Ok(queue.state())
+}
+
+/// Starts the next download. A paused item blocks the queue until it is resumed.
+fn process_queue(app: &AppHandle, process_paused: bool) -> Result<(), String> {
+    let mut queue = lock_queue()?;
+
+    if queue.active.is_some() {
+        return Ok(());
+    }
+
+    // Picks up an account switch that happened while the previous download was running
+    let mut process_paused = process_paused;
+    if let Some(config_path) = queue.config_path.clone() {
+        process_paused |= sync_account(app, &mut queue, &config_path)?;
+    }
+
+    let index = match queue
+        .items
+        .iter()
+        .position(|queued| queued.status == QueueItemStatus::Paused)
+    {
+        Some(index) if process_paused => index,
+        Some(_) => return Ok(()),
+        None => match queue
+            .items
+            .iter()
+            .position(|queued| queued.status == QueueItemStatus::Queued)
+        {
+            Some(index) => index,
+            None => return Ok(()),
+        },
+    };
+
+    let Some(config_path) = queue.config_path.clone() else {
+        return Ok(());
+    };
+
+    let resuming = queue.items[index].status == QueueItemStatus::Paused;
+
+    if !resuming {
+        queue.progress = Some(DownloadProgress::default());
+        queue.items[index].total_download_size = None;
+    }
+
+    if queue
+        .low_disk_space
+        .as_ref()
+        .is_some_and(|paused| paused.app_id == queue.items[index].item.id)
+    {
+        queue.low_disk_space = None;
+    }
+
+    let item = &mut queue.items[index];
+    item.status = QueueItemStatus::Downloading;
+    item.started_at = Some(now_ms());
+
+    let app_id = item.item.id.clone();
+    let stream_id = format!("install_{}_{}", app_id, now_ms());
+    // Legendary only reports the remaining size when resuming a paused download
+    let download_size = item.total_download_size;
+    let operation = download_operation(item);
+
+    match legendary::start_observed_stream(
+        app,
+        &config_path,
+        &stream_id,
+        &operation,
+        download_size,
+        true,
+    ) {
+        Ok(events) => {
+            queue.active = Some(ActiveDownload {
+                app_id: app_id.clone(),
+                stream_id,
+            });
+            queue.commit(app)?;
+
+            tauri::async_runtime::spawn(watch_download(app.clone(), app_id, events));
+
+            Ok(())
+        }
+        Err(_) => {
+            drop(queue);
+            finish_download(app, &app_id, false, None)
+        }
+    }
+}
+
+fn download_operation(item: &QueueItem) -> LegendaryOperation {
+    let app_id = item.item.id.clone();
+    let options = InstallOptions {
+        base_path: Some(item.options.base_path.clone()),
+        no_https: item.options.no_https,
+        skip_sdl: true,
+        skip_dlcs: true,
+        install_tags: item.options.install_tags.clone(),
+    };
+
+    match item.operation {
+        DownloadOperation::Install => LegendaryOperation::Install { app_id, options },
+        DownloadOperation::Update => LegendaryOperation::Update { app_id, options },
+        DownloadOperation::Repair => LegendaryOperation::Repair { app_id, options },
+    }
+}
+
+async fn watch_download(
+    app: AppHandle,
+    app_id: String,
+    mut events: UnboundedReceiver<StreamEvent>,
+) {
+    let mut success = false;
+    let mut reason = None;
+
+    while let Some(event) = events.recv().await {
+        match event.event_type {
+            EventType::Progress => {
+                if let Some(progress) = event.progress {
+                    update_progress(&app, &app_id, progress);
+                }
+            }
+            EventType::Terminated => {
+                success = event.code == Some(0);
+                reason = event.reason;
+                break;
+            }
+            EventType::Error => break,
+            _ => continue,
+        }
+    }
+
+    // Left as downloading, so the next start resumes it
+    if legendary::is_shutting_down() {
+        return;
+    }
+
+    if success {
+        install_selected_dlc(&app, &app_id).await;
+    }
+
+    let _ = finish_download(&app, &app_id, success, reason);
+}
+
+/// Installs the DLC chosen for the install once the base game is in place. A failed DLC
+/// does not fail the base game, its stream reports why.
+async fn install_selected_dlc(app: &AppHandle, app_id: &str) {
+    let selection = lock_queue().ok().and_then(|mut queue| {
+        let config_path = queue.config_path.clone()?;
+        let item = queue.item_mut(app_id)?;
+
+        (item.operation == DownloadOperation::Install && !item.options.dlc_ids.is_empty())
+            .then(|| (config_path, item.options.dlc_ids.clone()))
+    });
+
+    let Some((config_path, dlc_ids)) = selection else {
+        return;
+    };
+
+    let stream_id = format!("dlc_{}_{}", app_id, now_ms());
+    let _ = install::install_dlc(app, &config_path, app_id, &dlc_ids, &stream_id).await;
+}
+
+fn update_progress(app: &AppHandle, app_id: &str, progress: DownloadProgress) {
+    let Ok(mut queue) = lock_queue() else {
+        return;
+    };
+
+    if queue.active.as_ref().map(|active| active.app_id.as_str()) != Some(app_id) {
+        return;
+    }
+
+    if let Some(item) = queue.item_mut(app_id) {
+        item.total_download_size = Some(progress.total);
+    }
+
+    queue.progress = Some(progress);
+    queue.emit(app);
+}
+
+fn finish_download(
+    app: &AppHandle,
+    app_id: &str,
+    success: bool,
+    reason: Option<LegendaryFailure>,
+) -> Result<(), String> {
+    {
+        let mut queue = lock_queue()?;
+
+        if queue.active.as_ref().map(|active| active.app_id.as_str()) == Some(app_id) {
+            queue.active = None;
+        }
+
+        let Some(item) = queue.item_mut(app_id) else {
+            // Removed from the queue while it was downloading
+            queue.progress = None;
+            queue.emit(app);
+            drop(queue);
+
+            return process_queue(app, false);
+        };
+
+        if item.status == QueueItemStatus::Paused {
+            queue.emit(app);
+            return Ok(());
+        }
+
+        item.completed_at = Some(now_ms());
+
+        if success {
+            item.status = QueueItemStatus::Completed;
+            item.item.installed = Some(true);
+            item.item.has_update = Some(false);
+            item.item.requires_repair = Some(false);
+        } else {
+            item.status = QueueItemStatus::Failed;
+        }
+
+        let event = DownloadFinishedEvent {
+            item: item.item.clone(),
+            operation: item.operation,
+            success,
+            reason,
+        };
+
+        queue.progress = None;
+        queue.commit(app)?;
+
+        let _ = app.emit("download_finished", &event);
+    }
+
+    process_queue(app, false)
+}
+
+/// Polls the free space of the volume the active download writes to. When the space left
+/// after the download would drop below the threshold, e.g. because other software filled
+/// the disk, the download is paused and the user is offered to resume once space frees up.
+async fn watch_disk_space(app: AppHandle) {
+    loop {
+        tokio::time::sleep(DISK_SPACE_POLL_INTERVAL).await;
+        let _ = check_disk_space(&app).await;
+    }
+}
+
+async fn check_disk_space(app: &AppHandle) -> Result<(), String> {
+    let (app_id, base_path, threshold, remaining, paused) = {
+        let mut queue = lock_queue()?;
+
+        let percent = queue
+            .progress
+            .as_ref()
+            .map_or(0.0, |progress| progress.percent);
+        let watched = match (&queue.active, &queue.low_disk_space) {
+            (Some(active), _) => Some((active.app_id.clone(), None)),
+            (None, Some(paused)) if !paused.recovered => {
+                Some((paused.app_id.clone(), Some(paused.remaining)))
+            }
+            _ => None,
+        };
+
+        let Some((app_id, paused_remaining)) = watched else {
+            return Ok(());
+        };
+
+        let Some(item) = queue.item_mut(&app_id) else {
+            // Removed from the queue while it was paused
+            queue.low_disk_space = None;
+            return Ok(());
+        };
+
+        let base_path = item.options.base_path.clone();
+        let remaining = paused_remaining.unwrap_or_else(|| remaining_write(item, percent));
+
+        (
+            app_id,
+            base_path,
+            queue.disk_space_threshold,
+            remaining,
+            paused_remaining.is_some(),
+        )
+    };
+
+    let Some(directory) = install::nearest_existing(Path::new(&base_path)) else {
+        return Ok(());
+    };
+
+    let available = get_disk_space(directory.to_string_lossy().to_string())?.available;
+    let left_after = available.saturating_sub(remaining);
+    let event = DiskSpaceEvent {
+        app_id: app_id.clone(),
+        path: base_path,
+        available,
+        threshold,
+    };
+
+    if !paused && left_after < threshold {
+        lock_queue()?.low_disk_space = Some(LowDiskSpacePause {
+            app_id,
+            remaining,
+            recovered: false,
+        });
+
+        pause(app).await?;
+        let _ = app.emit("low_disk_space", &event);
+    } else if paused && left_after >= threshold {
+        if let Some(paused) = &mut lock_queue()?.low_disk_space {
+            paused.recovered = true;
+        }
+
+        let _ = app.emit("disk_space_recovered", &event);
+    }
+
+    Ok(())
+}
+
+/// Estimates how many bytes the download still has to write to disk. Updates and repairs
+/// only write about what they download, installs grow to the full install size. Items
+/// queued without an install size fall back to the download size.
+fn remaining_write(item: &QueueItem, percent: f64) -> u64 {
+    let download_size = item
+        .total_download_size
+        .or(item.item.download_size)
+        .unwrap_or_default();
+
+    let size = match item.operation {
+        DownloadOperation::Install if item.item.install_size > 0 => item.item.install_size,
+        _ => download_size,
+    };
+    let left = (100.0 - percent).clamp(0.0, 100.0) / 100.0;
+
+    (size as f64 * left) as u64
+}
+
+/// Loads the queue of the logged in account. Returns whether a download was interrupted
+/// by a restart, those are the only paused items that should resume on their own.
+fn sync_account(
+    app: &AppHandle,
+    queue: &mut DownloadQueue,
+    config_path: &str,
+) -> Result<bool, String> {
+    let account_id = legendary::get_account_id(config_path);
+
+    if queue.config_path.as_deref() == Some(config_path) && queue.account_id == account_id {
+        return Ok(false);
+    }
+
+    // The running download belongs to the previous account and finishes there
+    if queue.active.is_some() {
+        return Ok(false);
+    }
+
+    let mut persisted: PersistedQueue = storage::read_json(app, QUEUE_FILE)?;
+
+    queue.items = account_id
+        .as_ref()
+        .and_then(|account_id| persisted.queues.remove(account_id))
+        .unwrap_or_default();
+
+    // Downloads interrupted by a restart continue from where legendary left off
+    let mut interrupted = false;
+    for item in &mut queue.items {
+        if item.status == QueueItemStatus::Downloading {
+            item.status = QueueItemStatus::Paused;
+            interrupted = true;
+        }
+    }
+
+    queue.config_path = Some(config_path.to_string());
+    queue.account_id = account_id;
+    queue.progress = None;
+
+    Ok(interrupted)
+}
+
+impl DownloadQueue {
+    fn item_mut(&mut self, app_id: &str) -> Option<&mut QueueItem> {
+        self.items.iter_mut().find(|queued| {
+            queued.item.id == app_id
+                && matches!(
+                    queued.status,
+                    QueueItemStatus::Downloading | QueueItemStatus::Paused
+                )
+        })
+    }
+
+    fn state(&self) -> DownloadQueueState {
+        let downloading_app_id = match &self.active {
+            Some(active) => Some(active.app_id.clone()),
+            None => self
+                .items
+                .iter()
+                .find(|queued| queued.status == QueueItemStatus::Paused)
+                .map(|queued| queued.item.id.clone()),
+        };
+
+        DownloadQueueState {
+            items: self.items.clone(),
+            downloading_app_id,
+            progress: self.progress.clone(),
+            low_disk_space: self.low_disk_space.is_some(),
+        }
+    }
+
+    fn emit(&self, app: &AppHandle) {
+        let _ = app.emit("download_queue_changed", &self.state());
+    }
+
+    /// Saves the queue under the current account and notifies the frontend.
+    fn commit(&self, app: &AppHandle) -> Result<(), String> {
+        if let Some(account_id) = &self.account_id {
+            let mut persisted: PersistedQueue = storage::read_json(app, QUEUE_FILE)?;
+            persisted.config_path = self.config_path.clone();
+            persisted
+                .queues
+                .insert(account_id.clone(), self.items.clone());
+
+            storage::write_json(app, QUEUE_FILE, &persisted)?;
+        }
+
+        self.emit(app);
+
+        Ok(())
+    }
+}
+
+fn is_pending(status: QueueItemStatus) -> bool {
+    matches!(
+        status,
+        QueueItemStatus::Queued | QueueItemStatus::Downloading | QueueItemStatus::Paused
+    )
+}
+
+fn lock_queue() -> Result<MutexGuard<'static, DownloadQueue>, String> {
+    DOWNLOAD_QUEUE
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))
+}
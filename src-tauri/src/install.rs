@@ -0,0 +1,573 @@
+use crate::commands::get_disk_space;
+use crate::download_queue;
+use crate::legendary;
+use crate::storage;
+use crate::types::{
+    CommandOutput, DlcInfo, DlcResult, ImportResult, InstallOptions, InstallTag, InstallTagInfo,
+    LegendaryAppInfo, LegendaryDlcRef, LegendaryError, LegendaryFailure, LegendaryGame,
+    LegendaryManifestFile, LegendaryOperation, LegendaryTagSize, PreflightProblem, PreflightReport,
+    RunOptions, Volume,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use sysinfo::Disks;
+use tauri::AppHandle;
+
+const INSTALL_TAGS_FILE: &str = "install-tags.json";
+
+/// Filesystems sysinfo still reports on Linux that games can't be installed to.
+#[cfg(target_os = "linux")]
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "tmpfs",
+    "ramfs",
+    "overlay",
+    "efivarfs",
+    "tracefs",
+    "debugfs",
+    "securityfs",
+    "configfs",
+    "bpf",
+    "nsfs",
+    "fusectl",
+    "binfmt_misc",
+    "fuse.portal",
+    "fuse.gvfsd-fuse",
+    "fuse.snapfuse",
+];
+
+/// Checks whether `app_id` can be installed into `base_path` before a download is queued.
+/// Problems are collected instead of returned early so the UI can show all of them at once.
+pub async fn preflight_install(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    base_path: &str,
+    dlc_ids: &[String],
+) -> Result<PreflightReport, LegendaryError> {
+    let info = legendary::get_app_info(app, config_path, app_id, &RunOptions::default()).await?;
+    let mut problems = Vec::new();
+    let mut warnings = Vec::new();
+
+    let dlcs = get_dlc_infos(app, config_path, &info).await;
+    let mut dlc_download_size = 0;
+    let mut dlc_disk_size = 0;
+
+    for dlc_id in dlc_ids {
+        match dlcs.iter().find(|dlc| &dlc.app_id == dlc_id) {
+            Some(DlcInfo {
+                download_size: Some(download_size),
+                disk_size: Some(disk_size),
+                ..
+            }) => {
+                dlc_download_size += download_size;
+                dlc_disk_size += disk_size;
+            }
+            Some(_) => warnings.push(PreflightProblem::DlcSizeUnknown {
+                app_id: dlc_id.clone(),
+            }),
+            None => problems.push(PreflightProblem::DlcNotOwned {
+                app_id: dlc_id.clone(),
+            }),
+        }
+    }
+
+    if let Some(install) = &info.install {
+        problems.push(PreflightProblem::AlreadyInstalled {
+            install_path: install.install_path.clone(),
+        });
+    }
+
+    let (download_size, disk_size) = match &info.manifest {
+        Some(manifest) => (manifest.download_size, manifest.disk_size),
+        None => {
+            problems.push(PreflightProblem::ManifestUnavailable);
+            (0, 0)
+        }
+    };
+
+    let base_path = Path::new(base_path);
+    let install_path = install_folder(config_path, app_id).map(|folder| base_path.join(folder));
+    let mut available_space = None;
+
+    if !base_path.is_absolute() || base_path.is_file() {
+        problems.push(PreflightProblem::InvalidPath);
+    } else if let Some(existing) = nearest_existing(base_path) {
+        match get_disk_space(existing.to_string_lossy().to_string()) {
+            Ok(space) => {
+                let available = space.available;
+                let required = disk_size + dlc_disk_size;
+                let threshold = download_queue::get_disk_space_threshold().unwrap_or_default();
+                available_space = Some(available);
+
+                if available < required {
+                    problems.push(PreflightProblem::InsufficientSpace {
+                        required,
+                        available,
+                    });
+                } else if available - required < threshold {
+                    // The disk space watcher would pause the download before it finishes
+                    problems.push(PreflightProblem::BelowDiskSpaceThreshold {
+                        left: available - required,
+                        threshold,
+                    });
+                }
+            }
+            Err(message) => problems.push(PreflightProblem::DiskSpaceUnavailable { message }),
+        }
+
+        if let Err(message) = check_writable(&existing) {
+            problems.push(PreflightProblem::NotWritable { message });
+        }
+
+        if let Some(install_path) = &install_path {
+            if is_non_empty_dir(install_path) {
+                warnings.push(PreflightProblem::TargetNotEmpty {
+                    path: install_path.to_string_lossy().to_string(),
+                });
+            }
+        }
+    } else {
+        problems.push(PreflightProblem::InvalidPath);
+    }
+
+    Ok(PreflightReport {
+        app_id: app_id.to_string(),
+        install_path: install_path.map(|path| path.to_string_lossy().to_string()),
+        download_size,
+        disk_size,
+        dlc_download_size,
+        dlc_disk_size,
+        dlcs,
+        available_space,
+        can_install: problems.is_empty(),
+        problems,
+        warnings,
+    })
+}
+
+/// Selective download tags of an app with their sizes. The untagged base game files are
+/// always installed and not listed.
+pub async fn list_install_tags(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+) -> Result<InstallTagInfo, LegendaryError> {
+    let info = legendary::get_app_info(app, config_path, app_id, &RunOptions::default()).await?;
+
+    let tags = info
+        .manifest
+        .map(|manifest| {
+            let size_of = |sizes: &[LegendaryTagSize], tag: &str| {
+                sizes.iter().find(|size| size.tag == tag).cloned()
+            };
+
+            manifest
+                .install_tags
+                .iter()
+                .filter(|tag| !tag.is_empty())
+                .map(|tag| {
+                    let disk = size_of(&manifest.tag_disk_size, tag);
+                    let download = size_of(&manifest.tag_download_size, tag);
+
+                    InstallTag {
+                        tag: tag.clone(),
+                        download_size: download.map_or(0, |size| size.size),
+                        disk_size: disk.as_ref().map_or(0, |size| size.size),
+                        file_count: disk.map_or(0, |size| size.count),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let selected = get_saved_install_tags(app, app_id)
+        .map_err(|message| LegendaryError::Process { message })?
+        .or_else(|| {
+            info.install.map(|install| {
+                install
+                    .install_tags
+                    .into_iter()
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            })
+        });
+
+    Ok(InstallTagInfo {
+        app_id: app_id.to_string(),
+        tags,
+        selected,
+    })
+}
+
+pub fn get_saved_install_tags(
+    app: &AppHandle,
+    app_id: &str,
+) -> Result<Option<Vec<String>>, String> {
+    let saved: HashMap<String, Vec<String>> = storage::read_json(app, INSTALL_TAGS_FILE)?;
+
+    Ok(saved.get(app_id).cloned())
+}
+
+pub fn save_install_tags(
+    app: &AppHandle,
+    app_id: &str,
+    install_tags: &[String],
+) -> Result<(), String> {
+    let mut saved: HashMap<String, Vec<String>> = storage::read_json(app, INSTALL_TAGS_FILE)?;
+    saved.insert(app_id.to_string(), install_tags.to_vec());
+
+    storage::write_json(app, INSTALL_TAGS_FILE, &saved)
+}
+
+/// Owned DLC of an app and whether each one is installed.
+pub async fn list_dlc(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+) -> Result<Vec<DlcInfo>, LegendaryError> {
+    let info = legendary::get_app_info(app, config_path, app_id, &RunOptions::default()).await?;
+
+    Ok(get_dlc_infos(app, config_path, &info).await)
+}
+
+/// Installs DLC into the installed base game, one stream per DLC named
+/// `{stream_id}_{dlc id}`. A failed DLC does not stop the others.
+pub async fn install_dlc(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    dlc_ids: &[String],
+    stream_id: &str,
+) -> Result<Vec<DlcResult>, LegendaryError> {
+    let info = legendary::get_app_info(app, config_path, app_id, &RunOptions::default()).await?;
+
+    if info.install.is_none() {
+        return Err(LegendaryError::InvalidOperation {
+            message: format!("{} is not installed", app_id),
+        });
+    }
+
+    let owned: HashSet<&str> = info
+        .game
+        .owned_dlc
+        .iter()
+        .map(|dlc| dlc.app_name())
+        .collect();
+    check_dlc_ids(dlc_ids, &owned)?;
+
+    let mut results = Vec::new();
+
+    for dlc_id in dlc_ids {
+        let operation = LegendaryOperation::Install {
+            app_id: dlc_id.clone(),
+            options: InstallOptions {
+                skip_sdl: true,
+                ..InstallOptions::default()
+            },
+        };
+
+        let dlc_stream_id = format!("{}_{}", stream_id, dlc_id);
+        let output = match legendary::run_observed_stream(
+            app,
+            config_path,
+            &dlc_stream_id,
+            &operation,
+        )
+        .await
+        {
+            Err(LegendaryError::Cancelled) => return Err(LegendaryError::Cancelled),
+            output => output,
+        };
+
+        results.push(dlc_result(dlc_id, output));
+    }
+
+    Ok(results)
+}
+
+pub async fn uninstall_dlc(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    dlc_ids: &[String],
+) -> Result<Vec<DlcResult>, LegendaryError> {
+    let info = legendary::get_app_info(app, config_path, app_id, &RunOptions::default()).await?;
+
+    let installed: HashSet<&str> = info
+        .install
+        .iter()
+        .flat_map(|install| install.installed_dlc.iter().map(|dlc| dlc.app_name()))
+        .collect();
+    check_dlc_ids(dlc_ids, &installed)?;
+
+    let mut results = Vec::new();
+
+    for dlc_id in dlc_ids {
+        let operation = LegendaryOperation::Uninstall {
+            app_id: dlc_id.clone(),
+            keep_files: false,
+        };
+
+        let output =
+            legendary::run_legendary(app, config_path, &operation, &RunOptions::default()).await;
+
+        results.push(dlc_result(dlc_id, output));
+    }
+
+    Ok(results)
+}
+
+async fn get_dlc_infos(
+    app: &AppHandle,
+    config_path: &str,
+    info: &LegendaryAppInfo,
+) -> Vec<DlcInfo> {
+    let installed: HashSet<&str> = info
+        .install
+        .iter()
+        .flat_map(|install| install.installed_dlc.iter().map(|dlc| dlc.app_name()))
+        .collect();
+
+    let mut dlcs = Vec::new();
+
+    for dlc in &info.game.owned_dlc {
+        // A DLC legendary can't read is listed without a size instead of failing the others
+        let dlc_info =
+            legendary::get_app_info(app, config_path, dlc.app_name(), &RunOptions::default())
+                .await
+                .ok();
+        let manifest = dlc_info.as_ref().and_then(|info| info.manifest.as_ref());
+
+        let title = match (&dlc_info, dlc) {
+            (Some(dlc_info), _) => dlc_info.game.title.clone(),
+            (None, LegendaryDlcRef::Detailed { title, .. }) if !title.is_empty() => title.clone(),
+            (None, _) => dlc.app_name().to_string(),
+        };
+
+        dlcs.push(DlcInfo {
+            app_id: dlc.app_name().to_string(),
+            title,
+            installed: installed.contains(dlc.app_name()),
+            download_size: manifest.map(|manifest| manifest.download_size),
+            disk_size: manifest.map(|manifest| manifest.disk_size),
+        });
+    }
+
+    dlcs
+}
+
+fn check_dlc_ids(dlc_ids: &[String], allowed: &HashSet<&str>) -> Result<(), LegendaryError> {
+    match dlc_ids
+        .iter()
+        .find(|dlc_id| !allowed.contains(dlc_id.as_str()))
+    {
+        Some(dlc_id) => Err(LegendaryError::InvalidOperation {
+            message: format!("{} is not available for this app", dlc_id),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn dlc_result(dlc_id: &str, output: Result<CommandOutput, LegendaryError>) -> DlcResult {
+    let reason = match output {
+        Ok(output) if output.code == Some(0) => None,
+        Ok(output) => Some(output.error.unwrap_or(LegendaryFailure::Unknown)),
+        Err(LegendaryError::Failed { reason, .. }) => Some(reason),
+        Err(_) => Some(LegendaryFailure::Unknown),
+    };
+
+    DlcResult {
+        app_id: dlc_id.to_string(),
+        success: reason.is_none(),
+        reason,
+    }
+}
+
+/// Imports a game installed by another launcher or copied by hand. The files of the manifest
+/// are checked for presence and size first, the verify afterwards tells whether they are
+/// intact. The import and verify run as the streams `stream_id` and
+/// `{stream_id}_verify`.
+pub async fn import_installation(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    path: &str,
+    stream_id: &str,
+) -> Result<ImportResult, LegendaryError> {
+    let invalid = |message: String| LegendaryError::InvalidOperation { message };
+    let install_path = Path::new(path);
+
+    if !install_path.is_absolute() || !install_path.is_dir() {
+        return Err(invalid(format!("{} is not a directory", path)));
+    }
+
+    let info = legendary::get_app_info(app, config_path, app_id, &RunOptions::default()).await?;
+
+    if info.install.is_some() {
+        return Err(invalid(format!("{} is already installed", app_id)));
+    }
+
+    let Some(manifest) = info.manifest else {
+        return Err(invalid(format!("No manifest available for {}", app_id)));
+    };
+
+    if !install_path.join(&manifest.launch_exe).is_file() {
+        return Err(invalid(format!(
+            "{} does not contain {}",
+            path, manifest.launch_exe
+        )));
+    }
+
+    let files = legendary::get_app_files(app, config_path, app_id, &RunOptions::default()).await?;
+    check_manifest_files(install_path, &files).map_err(invalid)?;
+
+    let import = LegendaryOperation::Import {
+        app_id: app_id.to_string(),
+        path: path.to_string(),
+    };
+
+    let output = legendary::run_observed_stream(app, config_path, stream_id, &import).await?;
+
+    if output.code != Some(0) {
+        return Err(LegendaryError::Failed {
+            code: output.code,
+            reason: output.error.unwrap_or(LegendaryFailure::Unknown),
+            stderr: output.stderr,
+        });
+    }
+
+    let verify = LegendaryOperation::Verify {
+        app_id: app_id.to_string(),
+    };
+    let verify_stream_id = format!("{}_verify", stream_id);
+
+    let output =
+        legendary::run_observed_stream(app, config_path, &verify_stream_id, &verify).await?;
+    let needs_repair = output.error == Some(LegendaryFailure::NeedsRepair);
+
+    if output.code != Some(0) && !needs_repair {
+        return Err(LegendaryError::Failed {
+            code: output.code,
+            reason: output.error.unwrap_or(LegendaryFailure::Unknown),
+            stderr: output.stderr,
+        });
+    }
+
+    let info = legendary::get_app_info(app, config_path, app_id, &RunOptions::default()).await?;
+    let install = info
+        .install
+        .ok_or_else(|| invalid(format!("{} was not registered by legendary", app_id)))?;
+
+    Ok(ImportResult {
+        app_id: app_id.to_string(),
+        install_path: install.install_path,
+        version: install.version,
+        install_size: install.disk_size,
+        needs_repair,
+    })
+}
+
+/// Files of optional install tags may be left out, the base game files have to be there.
+/// A file that is there has to have the size from the manifest.
+fn check_manifest_files(
+    install_path: &Path,
+    files: &[LegendaryManifestFile],
+) -> Result<(), String> {
+    let mut missing = 0;
+    let mut mismatched = 0;
+
+    for file in files {
+        match fs::metadata(install_path.join(&file.filename)) {
+            Ok(metadata) if metadata.len() != file.file_size => mismatched += 1,
+            Ok(_) => {}
+            Err(_) if file.install_tags.is_empty() => missing += 1,
+            Err(_) => {}
+        }
+    }
+
+    if missing == 0 && mismatched == 0 {
+        return Ok(());
+    }
+
+    Err(format!(
+        "{} does not match the game files, {} missing and {} with a different size",
+        install_path.display(),
+        missing,
+        mismatched
+    ))
+}
+
+/// Legendary installs into `<base path>/<FolderName>`, falling back to the title. The
+/// metadata is cached by the `info` call that runs first.
+fn install_folder(config_path: &str, app_id: &str) -> Option<String> {
+    let path = Path::new(config_path)
+        .join("metadata")
+        .join(format!("{}.json", app_id));
+
+    let content = fs::read_to_string(path).ok()?;
+    let game: LegendaryGame = serde_json::from_str(&content).ok()?;
+
+    let folder = game
+        .metadata
+        .custom_attributes
+        .get("FolderName")
+        .map(|attribute| attribute.value.clone())
+        .unwrap_or(game.app_title);
+
+    Some(folder)
+}
+
+/// The base path is created by legendary, so space and permissions are checked on the
+/// closest directory that already exists.
+pub fn nearest_existing(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|ancestor| ancestor.is_dir())
+        .map(Path::to_path_buf)
+}
+
+fn check_writable(dir: &Path) -> Result<(), String> {
+    let probe = dir.join(format!(".spitfire-write-test-{}", std::process::id()));
+
+    fs::write(&probe, b"").map_err(|e| e.to_string())?;
+    fs::remove_file(&probe).map_err(|e| e.to_string())
+}
+
+fn is_non_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false)
+}
+
+pub fn list_volumes() -> Vec<Volume> {
+    let disks = Disks::new_with_refreshed_list();
+
+    disks
+        .list()
+        .iter()
+        .filter(|disk| disk.total_space() > 0 && !is_pseudo_filesystem(disk))
+        .map(|disk| Volume {
+            mount_point: disk.mount_point().to_string_lossy().to_string(),
+            label: disk.name().to_string_lossy().to_string(),
+            file_system: disk.file_system().to_string_lossy().to_string(),
+            total: disk.total_space(),
+            available: disk.available_space(),
+            removable: disk.is_removable(),
+            read_only: disk.is_read_only(),
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn is_pseudo_filesystem(disk: &sysinfo::Disk) -> bool {
+    let mount_point = disk.mount_point();
+
+    PSEUDO_FILESYSTEMS.contains(&disk.file_system().to_string_lossy().as_ref())
+        || mount_point.starts_with("/dev")
+        || mount_point.starts_with("/snap")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_pseudo_filesystem(_disk: &sysinfo::Disk) -> bool {
+    false
+}
@@ -0,0 +1,577 @@
+use crate::app_monitor;
+use crate::commands::get_disk_space;
+use crate::download_queue;
+use crate::install;
+use crate::legendary;
+use crate::storage;
+use crate::types::{
+    LegendaryError, LegendaryFailure, LegendaryOperation, MoveProgress, MoveResult, MoveStage,
+    RunOptions,
+};
+use crate::util::now_ms;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use tauri::{AppHandle, Emitter};
+
+const MOVES_FILE: &str = "pending-moves.json";
+const COPY_BUFFER_SIZE: usize = 8 * 1024 * 1024;
+const PROGRESS_INTERVAL_MS: u64 = 250;
+
+/// A move that has started. It is saved so a move interrupted by a restart can be resumed
+/// or rolled back later.
+#[derive(Serialize, Deserialize, Clone)]
+struct PendingMove {
+    source: String,
+    destination: String,
+    disk_size: u64,
+    /// Set before legendary is pointed at the destination, a rollback has to point it back.
+    registered: bool,
+}
+
+static RUNNING_MOVES: LazyLock<Mutex<HashSet<String>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+static CANCELLED_MOVES: LazyLock<Mutex<HashSet<String>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Removes the app from `RUNNING_MOVES` however the move ends.
+struct RunningMove(String);
+
+impl Drop for RunningMove {
+    fn drop(&mut self) {
+        RUNNING_MOVES.lock().unwrap().remove(&self.0);
+        CANCELLED_MOVES.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Copies the install to `new_base_path`, points legendary at the copy, verifies it and only
+/// then removes the source. Calling it again for an interrupted move resumes it, files that
+/// were copied completely are skipped.
+pub async fn move_installation(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    new_base_path: &str,
+) -> Result<MoveResult, LegendaryError> {
+    let invalid = |message: String| LegendaryError::InvalidOperation { message };
+
+    if !RUNNING_MOVES.lock().unwrap().insert(app_id.to_string()) {
+        return Err(invalid(format!("{} is already being moved", app_id)));
+    }
+
+    let _running = RunningMove(app_id.to_string());
+
+    let is_running = app_monitor::get_tracked_apps()
+        .map_err(|message| LegendaryError::Process { message })?
+        .iter()
+        .any(|tracked| tracked.app_id == app_id && tracked.is_running);
+
+    if is_running {
+        return Err(invalid(format!("{} is running", app_id)));
+    }
+
+    let is_downloading = download_queue::is_downloading(app_id)
+        .map_err(|message| LegendaryError::Process { message })?;
+
+    if is_downloading {
+        return Err(invalid(format!("{} is being downloaded", app_id)));
+    }
+
+    let pending = match get_pending_move(app, app_id)? {
+        Some(pending) => {
+            if Path::new(&pending.destination).parent() != Some(Path::new(new_base_path)) {
+                return Err(invalid(format!(
+                    "{} has an unfinished move to {}, resume or cancel it first",
+                    app_id, pending.destination
+                )));
+            }
+
+            pending
+        }
+        None => prepare_move(app, config_path, app_id, new_base_path).await?,
+    };
+
+    let result = run_move(app, config_path, app_id, &pending).await;
+
+    // Other failures keep the pending move, so calling this again continues the copy
+    match &result {
+        Err(LegendaryError::Cancelled) => {
+            // Read again, the move may have been registered before it was cancelled
+            if let Some(pending) = get_pending_move(app, app_id)? {
+                rollback(app, config_path, app_id, &pending).await?;
+            }
+        }
+        Err(_) => emit_progress(app, app_id, MoveStage::Failed, 0, pending.disk_size),
+        Ok(_) => {}
+    }
+
+    result
+}
+
+/// Cancels a running move, or rolls back one that was interrupted by a restart.
+pub async fn cancel_move(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+) -> Result<bool, LegendaryError> {
+    if RUNNING_MOVES.lock().unwrap().contains(app_id) {
+        CANCELLED_MOVES.lock().unwrap().insert(app_id.to_string());
+        return Ok(true);
+    }
+
+    match get_pending_move(app, app_id)? {
+        Some(pending) => {
+            rollback(app, config_path, app_id, &pending).await?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// App ids of moves that were interrupted and can be resumed or cancelled.
+pub fn get_pending_moves(app: &AppHandle) -> Result<HashMap<String, String>, String> {
+    let moves: HashMap<String, PendingMove> = storage::read_json(app, MOVES_FILE)?;
+
+    Ok(moves
+        .into_iter()
+        .map(|(app_id, pending)| (app_id, pending.destination))
+        .collect())
+}
+
+async fn prepare_move(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    new_base_path: &str,
+) -> Result<PendingMove, LegendaryError> {
+    let invalid = |message: String| LegendaryError::InvalidOperation { message };
+
+    let info = legendary::get_app_info(app, config_path, app_id, &RunOptions::default()).await?;
+    let Some(install) = info.install else {
+        return Err(invalid(format!("{} is not installed", app_id)));
+    };
+
+    let source = PathBuf::from(&install.install_path);
+    let new_base = Path::new(new_base_path);
+
+    let Some(folder) = source.file_name() else {
+        return Err(invalid(format!(
+            "Invalid install path {}",
+            install.install_path
+        )));
+    };
+
+    if !new_base.is_absolute() {
+        return Err(invalid(format!("Path must be absolute: {}", new_base_path)));
+    }
+
+    let destination = new_base.join(folder);
+
+    if destination.starts_with(&source) || source.starts_with(&destination) {
+        return Err(invalid(format!(
+            "{} overlaps the current install",
+            destination.display()
+        )));
+    }
+
+    let destination_used = fs::read_dir(&destination)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+
+    if destination_used {
+        return Err(invalid(format!(
+            "{} already exists and is not empty",
+            destination.display()
+        )));
+    }
+
+    let available = install::nearest_existing(new_base)
+        .and_then(|existing| get_disk_space(existing.to_string_lossy().to_string()).ok())
+        .map(|space| space.available)
+        .ok_or_else(|| invalid(format!("Could not read free space of {}", new_base_path)))?;
+
+    if available < install.disk_size {
+        return Err(LegendaryError::Failed {
+            code: None,
+            stderr: format!(
+                "Not enough available disk space: {} bytes needed, {} available",
+                install.disk_size, available
+            ),
+            reason: LegendaryFailure::InsufficientDiskSpace,
+        });
+    }
+
+    let pending = PendingMove {
+        source: install.install_path,
+        destination: destination.to_string_lossy().to_string(),
+        disk_size: install.disk_size,
+        registered: false,
+    };
+
+    save_pending_move(app, app_id, Some(&pending))?;
+
+    Ok(pending)
+}
+
+async fn run_move(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    pending: &PendingMove,
+) -> Result<MoveResult, LegendaryError> {
+    let mut pending = pending.clone();
+
+    if !pending.registered {
+        let copy_app = app.clone();
+        let copy_app_id = app_id.to_string();
+        let source = PathBuf::from(&pending.source);
+        let destination = PathBuf::from(&pending.destination);
+
+        tauri::async_runtime::spawn_blocking(move || {
+            copy_install(&copy_app, &copy_app_id, &source, &destination)
+        })
+        .await
+        .map_err(|e| LegendaryError::Process {
+            message: e.to_string(),
+        })??;
+
+        // Saved first, a rollback after an interrupted registration has to point legendary
+        // back at the source
+        pending.registered = true;
+        save_pending_move(app, app_id, Some(&pending))?;
+    }
+
+    // A resumed move registers again, the registration may not have finished before
+    emit_progress(app, app_id, MoveStage::Registering, 0, pending.disk_size);
+    register(app, config_path, app_id, &pending.destination).await?;
+
+    check_cancelled(app_id)?;
+    emit_progress(app, app_id, MoveStage::Verifying, 0, pending.disk_size);
+
+    let verify = LegendaryOperation::Verify {
+        app_id: app_id.to_string(),
+    };
+    let stream_id = format!("move_{}_{}_verify", app_id, now_ms());
+    let output = legendary::run_observed_stream(app, config_path, &stream_id, &verify).await?;
+
+    match output.error {
+        // A copy that does not verify is not worth resuming
+        Some(LegendaryFailure::NeedsRepair) => {
+            rollback(app, config_path, app_id, &pending).await?;
+
+            return Err(LegendaryError::Failed {
+                code: output.code,
+                stderr: output.stderr,
+                reason: LegendaryFailure::NeedsRepair,
+            });
+        }
+        // The verify itself failed, the move is kept so it can be verified again
+        Some(reason) => {
+            return Err(LegendaryError::Failed {
+                code: output.code,
+                stderr: output.stderr,
+                reason,
+            });
+        }
+        None => {}
+    }
+
+    // Cancelled while verifying, the source is still there to roll back to
+    check_cancelled(app_id)?;
+
+    // The game runs from the destination now, a failed cleanup only leaves the old files
+    emit_progress(app, app_id, MoveStage::RemovingSource, 0, pending.disk_size);
+    let source_removed = fs::remove_dir_all(&pending.source).is_ok();
+
+    save_pending_move(app, app_id, None)?;
+    emit_progress(
+        app,
+        app_id,
+        MoveStage::Completed,
+        pending.disk_size,
+        pending.disk_size,
+    );
+
+    Ok(MoveResult {
+        app_id: app_id.to_string(),
+        install_path: pending.destination,
+        source_removed,
+    })
+}
+
+/// Undoes a move that did not finish. The source is never touched before the move is
+/// verified, so only the copy and legendary's record have to be restored.
+async fn rollback(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    pending: &PendingMove,
+) -> Result<(), LegendaryError> {
+    emit_progress(app, app_id, MoveStage::RollingBack, 0, pending.disk_size);
+
+    if pending.registered {
+        register(app, config_path, app_id, &pending.source).await?;
+    }
+
+    let destination = Path::new(&pending.destination);
+    if destination.exists() {
+        fs::remove_dir_all(destination).map_err(|e| LegendaryError::Process {
+            message: e.to_string(),
+        })?;
+    }
+
+    save_pending_move(app, app_id, None)?;
+    emit_progress(app, app_id, MoveStage::RolledBack, 0, pending.disk_size);
+
+    Ok(())
+}
+
+/// Points legendary's install record at `install_path` without moving anything.
+async fn register(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    install_path: &str,
+) -> Result<(), LegendaryError> {
+    let Some(base_path) = Path::new(install_path).parent() else {
+        return Err(LegendaryError::InvalidOperation {
+            message: format!("Invalid install path {}", install_path),
+        });
+    };
+
+    let operation = LegendaryOperation::Move {
+        app_id: app_id.to_string(),
+        new_base_path: base_path.to_string_lossy().to_string(),
+        skip_move: true,
+    };
+
+    let output =
+        legendary::run_legendary(app, config_path, &operation, &RunOptions::default()).await?;
+
+    if output.code != Some(0) {
+        return Err(LegendaryError::Failed {
+            code: output.code,
+            reason: output.error.unwrap_or(LegendaryFailure::Unknown),
+            stderr: output.stderr,
+        });
+    }
+
+    Ok(())
+}
+
+struct CopyProgress<'a> {
+    app: &'a AppHandle,
+    app_id: &'a str,
+    copied: u64,
+    total: u64,
+    last_emit: u64,
+}
+
+impl CopyProgress<'_> {
+    fn add(&mut self, bytes: u64) {
+        self.copied += bytes;
+
+        let now = now_ms();
+        if now.saturating_sub(self.last_emit) >= PROGRESS_INTERVAL_MS {
+            self.last_emit = now;
+            emit_progress(
+                self.app,
+                self.app_id,
+                MoveStage::Copying,
+                self.copied,
+                self.total,
+            );
+        }
+    }
+}
+
+fn copy_install(
+    app: &AppHandle,
+    app_id: &str,
+    source: &Path,
+    destination: &Path,
+) -> Result<(), LegendaryError> {
+    let io_error = |e: std::io::Error| LegendaryError::Process {
+        message: e.to_string(),
+    };
+
+    let mut progress = CopyProgress {
+        app,
+        app_id,
+        copied: 0,
+        total: directory_size(source).map_err(io_error)?,
+        last_emit: 0,
+    };
+
+    copy_directory(app_id, source, destination, &mut progress)?;
+    emit_progress(
+        app,
+        app_id,
+        MoveStage::Copying,
+        progress.total,
+        progress.total,
+    );
+
+    Ok(())
+}
+
+fn copy_directory(
+    app_id: &str,
+    source: &Path,
+    destination: &Path,
+    progress: &mut CopyProgress,
+) -> Result<(), LegendaryError> {
+    let io_error = |e: std::io::Error| LegendaryError::Process {
+        message: e.to_string(),
+    };
+
+    fs::create_dir_all(destination).map_err(io_error)?;
+
+    for entry in fs::read_dir(source).map_err(io_error)? {
+        let entry = entry.map_err(io_error)?;
+        let target = destination.join(entry.file_name());
+        let metadata = fs::symlink_metadata(entry.path()).map_err(io_error)?;
+
+        // Links are recreated as links, following them could copy files twice or loop
+        if metadata.is_symlink() {
+            if fs::symlink_metadata(&target).is_err() {
+                copy_symlink(&entry.path(), &target).map_err(io_error)?;
+            }
+
+            continue;
+        }
+
+        if metadata.is_dir() {
+            copy_directory(app_id, &entry.path(), &target, progress)?;
+            continue;
+        }
+
+        // Files from an earlier attempt are only skipped when they were copied completely
+        let copied = fs::metadata(&target)
+            .map(|existing| existing.len() == metadata.len())
+            .unwrap_or(false);
+
+        if copied {
+            progress.add(metadata.len());
+            continue;
+        }
+
+        copy_file(app_id, &entry.path(), &target, progress)?;
+        fs::set_permissions(&target, metadata.permissions()).map_err(io_error)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, destination)
+}
+
+#[cfg(windows)]
+fn copy_symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let link = fs::read_link(source)?;
+
+    // Windows has separate links for files and directories, a dangling link becomes a file link
+    if fs::metadata(source).is_ok_and(|metadata| metadata.is_dir()) {
+        std::os::windows::fs::symlink_dir(link, destination)
+    } else {
+        std::os::windows::fs::symlink_file(link, destination)
+    }
+}
+
+fn copy_file(
+    app_id: &str,
+    source: &Path,
+    destination: &Path,
+    progress: &mut CopyProgress,
+) -> Result<(), LegendaryError> {
+    let io_error = |e: std::io::Error| LegendaryError::Process {
+        message: e.to_string(),
+    };
+
+    let mut reader = File::open(source).map_err(io_error)?;
+    let mut writer = File::create(destination).map_err(io_error)?;
+    let mut buffer = vec![0; COPY_BUFFER_SIZE];
+
+    loop {
+        check_cancelled(app_id)?;
+
+        let read = reader.read(&mut buffer).map_err(io_error)?;
+        if read == 0 {
+            break;
+        }
+
+        writer.write_all(&buffer[..read]).map_err(io_error)?;
+        progress.add(read as u64);
+    }
+
+    writer.sync_all().map_err(io_error)
+}
+
+fn directory_size(path: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = fs::symlink_metadata(entry.path())?;
+
+        size += if metadata.is_symlink() {
+            0
+        } else if metadata.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+fn check_cancelled(app_id: &str) -> Result<(), LegendaryError> {
+    if CANCELLED_MOVES.lock().unwrap().contains(app_id) {
+        return Err(LegendaryError::Cancelled);
+    }
+
+    Ok(())
+}
+
+fn emit_progress(app: &AppHandle, app_id: &str, stage: MoveStage, copied: u64, total: u64) {
+    let progress = MoveProgress {
+        app_id: app_id.to_string(),
+        stage,
+        copied,
+        total,
+    };
+
+    let _ = app.emit("move_progress", &progress);
+}
+
+fn get_pending_move(app: &AppHandle, app_id: &str) -> Result<Option<PendingMove>, LegendaryError> {
+    let mut moves: HashMap<String, PendingMove> = storage::read_json(app, MOVES_FILE)
+        .map_err(|message| LegendaryError::Process { message })?;
+
+    Ok(moves.remove(app_id))
+}
+
+fn save_pending_move(
+    app: &AppHandle,
+    app_id: &str,
+    pending: Option<&PendingMove>,
+) -> Result<(), LegendaryError> {
+    let update = || -> Result<(), String> {
+        let mut moves: HashMap<String, PendingMove> = storage::read_json(app, MOVES_FILE)?;
+
+        match pending {
+            Some(pending) => moves.insert(app_id.to_string(), pending.clone()),
+            None => moves.remove(app_id),
+        };
+
+        storage::write_json(app, MOVES_FILE, &moves)
+    };
+
+    update().map_err(|message| LegendaryError::Process { message })
+}
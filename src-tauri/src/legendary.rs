@@ -1,44 +1,204 @@
-use crate::types::{CommandOutput, EventType, StreamEvent};
-use std::collections::HashMap;
-use std::os::windows::process::CommandExt;
-use std::process::Command;
-use std::sync::{LazyLock, Mutex};
+use crate::logs::LogWriter;
+use crate::storage;
+use crate::types::{
+    AuthAction, CommandAccess, CommandOutput, DownloadProgress, EventType, InstallOptions,
+    LaunchData, LegendaryAppInfo, LegendaryError, LegendaryFailure, LegendaryGame,
+    LegendaryInstalledApp, LegendaryManifestFile, LegendaryOperation, LegendaryStatus, RunOptions,
+    SaveSyncDirection, ScheduledCommand, SchedulerEvent, StreamEvent, StreamInfo, StreamState,
+    TerminationReason,
+};
+use crate::util::now_ms;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::async_runtime::Receiver;
 use tauri::{AppHandle, Emitter};
-use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::process::{Command, CommandChild, CommandEvent};
 use tauri_plugin_shell::ShellExt;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
 
-static ACTIVE_STREAMS: LazyLock<Mutex<HashMap<String, CommandChild>>> =
+/// Grace period legendary gets to write its resume file before being killed.
+pub const DEFAULT_GRACE_PERIOD_MS: u64 = 5000;
+
+/// Events kept per stream for listeners that attach after they were emitted.
+const STREAM_BACKLOG_SIZE: usize = 1000;
+
+/// Finished streams keep their backlog so a reloaded webview can still see how they ended.
+const FINISHED_STREAMS_KEPT: usize = 16;
+
+/// Stderr kept per stream to classify its failure, the errors are always near the end.
+const STDERR_TAIL_SIZE: usize = 64 * 1024;
+
+/// Shared so stdin can be written without holding `ACTIVE_STREAMS`.
+type StreamChild = Arc<Mutex<Option<CommandChild>>>;
+
+struct ActiveStream {
+    /// Set while legendary is running, `None` while the stream is queued or once it exited.
+    child: StreamChild,
+    args: Vec<String>,
+    state: StreamState,
+    pid: Option<u32>,
+    created_at: u64,
+    started_at: Option<u64>,
+    output_bytes: u64,
+    last_progress: Option<DownloadProgress>,
+    backlog: VecDeque<StreamEvent>,
+    next_seq: u64,
+}
+
+static ACTIVE_STREAMS: LazyLock<Mutex<HashMap<String, ActiveStream>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static FINISHED_STREAMS: LazyLock<Mutex<VecDeque<String>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+/// PIDs of the short-lived `run_legendary` processes, so they can be killed with the streams.
+static RUNNING_COMMANDS: LazyLock<Mutex<HashSet<u32>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// How each stream that is being stopped was asked to stop, reported in its `Terminated` event.
+static STOP_REASONS: LazyLock<Mutex<HashMap<String, TerminationReason>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Cancellation handles of the `run_legendary` calls that were given a request id.
+/// The entry of a cancelled request stays until the call returns, so its id can't be reused
+/// while the process is still being stopped.
+static PENDING_REQUESTS: LazyLock<Mutex<HashMap<String, Option<oneshot::Sender<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Streams that are still waiting for their turn in the scheduler, so they can be stopped
+/// before legendary is started.
+static QUEUED_STREAMS: LazyLock<Mutex<HashMap<String, oneshot::Sender<()>>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
+/// Runs legendary to completion. The process is killed if the call times out, is cancelled
+/// through [`cancel_legendary`] or is dropped before legendary exits. The timeout includes
+/// the time spent waiting for the scheduler.
 pub async fn run_legendary(
     app: &AppHandle,
     config_path: &str,
-    args: &[String],
-) -> Result<CommandOutput, String> {
-    let sidecar = create_legendary_sidecar(app, config_path, args)?;
-    let (mut rx, _child) = sidecar.spawn().map_err(|e| e.to_string())?;
+    operation: &LegendaryOperation,
+    options: &RunOptions,
+) -> Result<CommandOutput, LegendaryError> {
+    let args = operation_args(operation)
+        .map_err(|message| LegendaryError::InvalidOperation { message })?;
+    let sidecar = create_legendary_sidecar(app, config_path, &args)
+        .map_err(|message| LegendaryError::Process { message })?;
+
+    let (cancel_tx, mut cancel_rx) = oneshot::channel();
+    let _request = PendingRequest::new(options.request_id.clone(), cancel_tx)
+        .map_err(|message| LegendaryError::InvalidOperation { message })?;
+
+    let scheduler_id = options
+        .request_id
+        .clone()
+        .unwrap_or_else(|| format!("run_{}", NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed)));
+
+    let deadline = options
+        .timeout_ms
+        .map(|timeout_ms| tokio::time::Instant::now() + Duration::from_millis(timeout_ms));
+    let timed_out = || LegendaryError::TimedOut {
+        timeout_ms: options.timeout_ms.unwrap_or_default(),
+    };
+
+    let slot = async {
+        match deadline {
+            Some(deadline) => tokio::time::timeout_at(
+                deadline,
+                acquire_slot(app, config_path, &scheduler_id, &args),
+            )
+            .await
+            .ok(),
+            None => Some(acquire_slot(app, config_path, &scheduler_id, &args).await),
+        }
+    };
+
+    let _slot = tokio::select! {
+        slot = slot => slot.ok_or_else(timed_out)?,
+        _ = &mut cancel_rx => return Err(LegendaryError::Cancelled),
+    };
+
+    let (mut rx, child) = sidecar.spawn().map_err(|e| LegendaryError::Process {
+        message: e.to_string(),
+    })?;
+    let mut tracked = TrackedCommand::new(child);
+
+    let mut log = options
+        .log
+        .then(|| LogWriter::create(app, &args).ok())
+        .flatten();
+
+    let output = async {
+        tokio::select! {
+            output = collect_output(&mut rx, &mut log) => output,
+            _ = cancel_rx => Err(LegendaryError::Cancelled),
+        }
+    };
+
+    let result = match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, output)
+            .await
+            .unwrap_or_else(|_| Err(timed_out())),
+        None => output.await,
+    };
+
+    if !matches!(
+        result,
+        Err(LegendaryError::Cancelled | LegendaryError::TimedOut { .. })
+    ) {
+        tracked.finished = true;
+    }
+
+    if let Some(log) = &mut log {
+        match &result {
+            Ok(output) => log.write_line(&format!("Exited with code {:?}", output.code)),
+            Err(e) => log.write_line(&e.to_string()),
+        }
+    }
 
+    result
+}
+
+async fn collect_output(
+    rx: &mut Receiver<CommandEvent>,
+    log: &mut Option<LogWriter>,
+) -> Result<CommandOutput, LegendaryError> {
     let mut stdout = String::new();
     let mut stderr = String::new();
 
     while let Some(event) = rx.recv().await {
         match event {
             CommandEvent::Stdout(bytes) => {
+                if let Some(log) = log {
+                    log.write(&bytes);
+                }
                 stdout.push_str(&String::from_utf8_lossy(&bytes));
             }
             CommandEvent::Stderr(bytes) => {
+                if let Some(log) = log {
+                    log.write(&bytes);
+                }
                 stderr.push_str(&String::from_utf8_lossy(&bytes));
             }
             CommandEvent::Terminated(payload) => {
                 return Ok(CommandOutput {
                     code: payload.code,
                     signal: payload.signal,
+                    error: classify_failure(payload.code, &stderr),
                     stdout: stdout.trim().to_string(),
                     stderr: stderr.trim().to_string(),
                 });
             }
             CommandEvent::Error(error) => {
-                return Err(format!("Command error: {}", error));
+                return Err(LegendaryError::Process {
+                    message: format!("Command error: {}", error),
+                });
             }
             _ => continue,
         }
@@ -47,140 +207,1894 @@ pub async fn run_legendary(
     Ok(CommandOutput {
         code: None,
         signal: None,
+        error: classify_failure(None, &stderr),
         stdout: stdout.trim().to_string(),
         stderr: stderr.trim().to_string(),
     })
 }
 
-pub async fn start_legendary_stream(
+/// Known stderr messages, checked in order.
+const FAILURE_PATTERNS: &[(LegendaryFailure, &[&str])] = &[
+    (
+        LegendaryFailure::InsufficientDiskSpace,
+        &["not enough available disk space", "no space left on device"],
+    ),
+    (
+        LegendaryFailure::TokenExpired,
+        &[
+            "no longer valid",
+            "session expired",
+            "invalid_refresh_token",
+            "token_verification_failed",
+        ],
+    ),
+    (
+        LegendaryFailure::NotLoggedIn,
+        &[
+            "login failed",
+            "not logged in",
+            "no saved credentials",
+            "user data is not available",
+        ],
+    ),
+    (
+        LegendaryFailure::NotOwned,
+        &[
+            "in list of available games",
+            "did you type the name correctly",
+            "not owned",
+        ],
+    ),
+    (
+        LegendaryFailure::NeedsRepair,
+        &[
+            "repair your game installation",
+            "verification failed",
+            "needs to be verified",
+        ],
+    ),
+    (
+        LegendaryFailure::InstallPathConflict,
+        &[
+            "already exists",
+            "is not empty",
+            "not a directory",
+            "is already installed",
+        ],
+    ),
+    (
+        LegendaryFailure::Network,
+        &[
+            "connectionerror",
+            "connection aborted",
+            "failed to establish a new connection",
+            "max retries exceeded",
+            "read timed out",
+            "name resolution",
+            "failed to download manifest",
+            "failed to fetch",
+            "unable to get manifest",
+            "connection refused",
+        ],
+    ),
+];
+
+/// Maps legendary's stderr to a `LegendaryFailure`, `None` if the command succeeded
+/// without reporting anything known.
+pub fn classify_failure(code: Option<i32>, stderr: &str) -> Option<LegendaryFailure> {
+    let stderr = stderr.to_lowercase();
+
+    let known = FAILURE_PATTERNS.iter().find_map(|(failure, patterns)| {
+        patterns
+            .iter()
+            .any(|pattern| stderr.contains(pattern))
+            .then_some(*failure)
+    });
+
+    // Legendary logs everything to stderr, so a successful run is only checked for the one
+    // problem it reports with a zero exit code: a failed verify
+    if code == Some(0) {
+        return known.filter(|failure| *failure == LegendaryFailure::NeedsRepair);
+    }
+
+    Some(known.unwrap_or(LegendaryFailure::Unknown))
+}
+
+pub fn cancel_legendary(request_id: &str) -> bool {
+    let sender = PENDING_REQUESTS
+        .lock()
+        .unwrap()
+        .get_mut(request_id)
+        .and_then(Option::take);
+
+    sender.is_some_and(|sender| sender.send(()).is_ok())
+}
+
+/// Keeps a `run_legendary` request id cancellable for as long as the call is running.
+struct PendingRequest(Option<String>);
+
+impl PendingRequest {
+    /// A second call with an id that is still running would take over its cancellation.
+    fn new(request_id: Option<String>, cancel: oneshot::Sender<()>) -> Result<Self, String> {
+        if let Some(request_id) = &request_id {
+            let mut requests = PENDING_REQUESTS.lock().unwrap();
+
+            if requests.contains_key(request_id) {
+                return Err(format!("Request {} is already running", request_id));
+            }
+
+            requests.insert(request_id.clone(), Some(cancel));
+        }
+
+        Ok(Self(request_id))
+    }
+}
+
+impl Drop for PendingRequest {
+    fn drop(&mut self) {
+        if let Some(request_id) = &self.0 {
+            PENDING_REQUESTS.lock().unwrap().remove(request_id);
+        }
+    }
+}
+
+/// Keeps a `run_legendary` process registered while the call is waiting on it, and kills
+/// it if the call ends before legendary does.
+struct TrackedCommand {
+    child: Option<CommandChild>,
+    finished: bool,
+}
+
+impl TrackedCommand {
+    fn new(child: CommandChild) -> Self {
+        RUNNING_COMMANDS.lock().unwrap().insert(child.pid());
+
+        Self {
+            child: Some(child),
+            finished: false,
+        }
+    }
+}
+
+impl Drop for TrackedCommand {
+    fn drop(&mut self) {
+        let Some(child) = self.child.take() else {
+            return;
+        };
+
+        // Missing from the set means kill_legendary_processes already took care of it
+        let tracked = RUNNING_COMMANDS.lock().unwrap().remove(&child.pid());
+
+        if tracked && !self.finished {
+            kill_descendants(child.pid());
+            let _ = child.kill();
+        }
+    }
+}
+
+pub async fn get_list(
     app: &AppHandle,
     config_path: &str,
-    stream_id: &str,
-    args: &[String],
-) -> Result<String, String> {
-    let sidecar = create_legendary_sidecar(app, config_path, args)?;
-    let (mut rx, child) = sidecar.spawn().map_err(|e| e.to_string())?;
+    options: &RunOptions,
+) -> Result<Vec<LegendaryGame>, LegendaryError> {
+    run_legendary_json(app, config_path, &LegendaryOperation::List, options).await
+}
 
-    {
-        let mut streams = ACTIVE_STREAMS.lock().unwrap();
-        streams.insert(stream_id.to_string(), child);
+pub async fn get_status(
+    app: &AppHandle,
+    config_path: &str,
+    options: &RunOptions,
+) -> Result<LegendaryStatus, LegendaryError> {
+    let mut status: LegendaryStatus =
+        run_legendary_json(app, config_path, &LegendaryOperation::Status, options).await?;
+
+    if status.account.as_deref() == Some("<not logged in>") {
+        status.account = None;
     }
 
-    let stream_id_clone = stream_id.to_string();
-    let app_clone = app.clone();
+    Ok(status)
+}
 
-    tauri::async_runtime::spawn(async move {
-        let event_name = format!("legendary_stream:{}", stream_id_clone);
+pub async fn get_app_info(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    options: &RunOptions,
+) -> Result<LegendaryAppInfo, LegendaryError> {
+    let operation = LegendaryOperation::Info {
+        app_id: app_id.to_string(),
+    };
 
-        while let Some(event) = rx.recv().await {
-            let stream_event = match event {
-                CommandEvent::Stdout(bytes) => StreamEvent {
-                    stream_id: stream_id_clone.clone(),
-                    event_type: EventType::Stdout,
-                    data: String::from_utf8_lossy(&bytes).to_string(),
-                    code: None,
-                    signal: None,
-                },
-                CommandEvent::Stderr(bytes) => StreamEvent {
-                    stream_id: stream_id_clone.clone(),
-                    event_type: EventType::Stderr,
-                    data: String::from_utf8_lossy(&bytes).to_string(),
-                    code: None,
-                    signal: None,
-                },
-                CommandEvent::Terminated(payload) => {
-                    let event = StreamEvent {
-                        stream_id: stream_id_clone.clone(),
-                        event_type: EventType::Terminated,
-                        data: String::new(),
-                        code: payload.code,
-                        signal: payload.signal,
-                    };
+    run_legendary_json(app, config_path, &operation, options).await
+}
 
-                    {
-                        let mut streams = ACTIVE_STREAMS.lock().unwrap();
-                        streams.remove(&stream_id_clone);
-                    }
+pub async fn get_app_files(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    options: &RunOptions,
+) -> Result<Vec<LegendaryManifestFile>, LegendaryError> {
+    let operation = LegendaryOperation::ListFiles {
+        app_id: app_id.to_string(),
+    };
 
-                    let _ = app_clone.emit(&event_name, &event);
-                    break;
-                }
-                CommandEvent::Error(error) => {
-                    let event = StreamEvent {
-                        stream_id: stream_id_clone.clone(),
-                        event_type: EventType::Error,
-                        data: error,
-                        code: None,
-                        signal: None,
-                    };
+    run_legendary_json(app, config_path, &operation, options).await
+}
+
+pub async fn get_installed_list(
+    app: &AppHandle,
+    config_path: &str,
+    options: &RunOptions,
+) -> Result<Vec<LegendaryInstalledApp>, LegendaryError> {
+    run_legendary_json(
+        app,
+        config_path,
+        &LegendaryOperation::ListInstalled,
+        options,
+    )
+    .await
+}
+
+pub async fn get_launch_data(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    options: &RunOptions,
+) -> Result<LaunchData, LegendaryError> {
+    let operation = LegendaryOperation::Launch {
+        app_id: app_id.to_string(),
+    };
+
+    let mut launch_data: LaunchData =
+        run_legendary_json(app, config_path, &operation, options).await?;
+
+    launch_data.game_id = app_id.to_string();
+
+    Ok(launch_data)
+}
+
+/// Builds the sidecar arguments for an operation. This is the only place they are put
+/// together, so every value from the webview is checked here.
+pub fn operation_args(operation: &LegendaryOperation) -> Result<Vec<String>, String> {
+    let args = match operation {
+        LegendaryOperation::Install { app_id, options } => {
+            install_args("install", app_id, options)?
+        }
+        LegendaryOperation::Update { app_id, options } => install_args("update", app_id, options)?,
+        LegendaryOperation::Repair { app_id, options } => install_args("repair", app_id, options)?,
+        LegendaryOperation::Verify { app_id } => vec![
+            "verify".to_string(),
+            app_id_arg(app_id)?,
+            "-y".to_string(),
+            "--skip-sdl".to_string(),
+        ],
+        LegendaryOperation::Uninstall { app_id, keep_files } => {
+            let mut args = vec![
+                "uninstall".to_string(),
+                app_id_arg(app_id)?,
+                "-y".to_string(),
+            ];
+
+            if *keep_files {
+                args.push("--keep-files".to_string());
+            }
+
+            args
+        }
+        LegendaryOperation::Import { app_id, path } => {
+            vec!["import".to_string(), app_id_arg(app_id)?, path_arg(path)?]
+        }
+        LegendaryOperation::Move {
+            app_id,
+            new_base_path,
+            skip_move,
+        } => {
+            let mut args = vec![
+                "move".to_string(),
+                app_id_arg(app_id)?,
+                path_arg(new_base_path)?,
+            ];
+
+            if *skip_move {
+                args.push("--skip-move".to_string());
+            }
+
+            args
+        }
+        LegendaryOperation::Launch { app_id } => vec![
+            "launch".to_string(),
+            app_id_arg(app_id)?,
+            "--dry-run".to_string(),
+            "--json".to_string(),
+        ],
+        LegendaryOperation::Auth {
+            action: AuthAction::Login { exchange_code },
+        } => {
+            let valid = !exchange_code.is_empty()
+                && exchange_code.chars().all(|c| c.is_ascii_alphanumeric());
+
+            if !valid {
+                return Err("Invalid exchange code".to_string());
+            }
+
+            vec![
+                "auth".to_string(),
+                "--token".to_string(),
+                exchange_code.clone(),
+            ]
+        }
+        LegendaryOperation::Auth {
+            action: AuthAction::Logout,
+        } => vec!["auth".to_string(), "--delete".to_string()],
+        LegendaryOperation::EglSync => vec![
+            "egl-sync".to_string(),
+            "-y".to_string(),
+            "--enable-sync".to_string(),
+        ],
+        LegendaryOperation::SyncSaves {
+            app_id,
+            direction,
+            force,
+            interactive,
+        } => {
+            let mut args = vec!["sync-saves".to_string(), app_id_arg(app_id)?];
 
-                    {
-                        let mut streams = ACTIVE_STREAMS.lock().unwrap();
-                        streams.remove(&stream_id_clone);
+            if !*interactive {
+                args.push("-y".to_string());
+            }
+
+            match direction {
+                SaveSyncDirection::Upload => {
+                    args.push("--skip-download".to_string());
+
+                    if *force {
+                        args.push("--force-upload".to_string());
                     }
+                }
+                SaveSyncDirection::Download => {
+                    args.push("--skip-upload".to_string());
 
-                    let _ = app_clone.emit(&event_name, &event);
-                    break;
+                    if *force {
+                        args.push("--force-download".to_string());
+                    }
                 }
-                _ => continue,
-            };
+                SaveSyncDirection::Auto => {}
+            }
 
-            let _ = app_clone.emit(&event_name, &stream_event);
+            args
         }
-    });
+        LegendaryOperation::Info { app_id } => {
+            vec![
+                "info".to_string(),
+                app_id_arg(app_id)?,
+                "--json".to_string(),
+            ]
+        }
+        LegendaryOperation::ListFiles { app_id } => {
+            vec![
+                "list-files".to_string(),
+                app_id_arg(app_id)?,
+                "--json".to_string(),
+            ]
+        }
+        LegendaryOperation::List => vec!["list".to_string(), "--json".to_string()],
+        LegendaryOperation::ListInstalled => {
+            vec!["list-installed".to_string(), "--json".to_string()]
+        }
+        LegendaryOperation::Status => vec!["status".to_string(), "--json".to_string()],
+    };
+
+    Ok(args)
+}
+
+fn install_args(
+    command: &str,
+    app_id: &str,
+    options: &InstallOptions,
+) -> Result<Vec<String>, String> {
+    let mut args = vec![command.to_string(), app_id_arg(app_id)?, "-y".to_string()];
+
+    if let Some(install_tags) = &options.install_tags {
+        // Files without a tag belong to the base game and are only kept with the empty tag
+        args.extend(["--install-tag".to_string(), String::new()]);
+
+        for tag in install_tags.iter().filter(|tag| !tag.is_empty()) {
+            args.extend(["--install-tag".to_string(), install_tag_arg(tag)?]);
+        }
+    } else if options.skip_sdl {
+        args.push("--skip-sdl".to_string());
+    }
+
+    if options.skip_dlcs {
+        args.push("--skip-dlcs".to_string());
+    }
+
+    if let Some(base_path) = &options.base_path {
+        args.extend(["--base-path".to_string(), path_arg(base_path)?]);
+    }
+
+    if options.no_https {
+        args.push("--no-https".to_string());
+    }
+
+    Ok(args)
+}
+
+/// App ids are Epic's alphanumeric app names, anything else could be read as a flag.
+fn app_id_arg(app_id: &str) -> Result<String, String> {
+    let valid = !app_id.is_empty()
+        && !app_id.starts_with('-')
+        && app_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+    if !valid {
+        return Err(format!("Invalid app id: {}", app_id));
+    }
+
+    Ok(app_id.to_string())
+}
+
+fn install_tag_arg(tag: &str) -> Result<String, String> {
+    if tag.starts_with('-') || tag.contains(',') {
+        return Err(format!("Invalid install tag: {}", tag));
+    }
+
+    Ok(tag.to_string())
+}
+
+/// Only absolute paths are accepted, which also keeps them from being read as flags.
+fn path_arg(path: &str) -> Result<String, String> {
+    if !Path::new(path).is_absolute() {
+        return Err(format!("Path must be absolute: {}", path));
+    }
+
+    Ok(path.to_string())
+}
+
+async fn run_legendary_json<T: DeserializeOwned>(
+    app: &AppHandle,
+    config_path: &str,
+    operation: &LegendaryOperation,
+    options: &RunOptions,
+) -> Result<T, LegendaryError> {
+    let output = run_legendary(app, config_path, operation, options).await?;
+
+    if output.code != Some(0) {
+        return Err(LegendaryError::Failed {
+            code: output.code,
+            reason: output.error.unwrap_or(LegendaryFailure::Unknown),
+            stderr: output.stderr,
+        });
+    }
+
+    serde_json::from_str(&output.stdout).map_err(|e| LegendaryError::InvalidOutput {
+        command: operation_args(operation).unwrap_or_default().join(" "),
+        message: e.to_string(),
+    })
+}
+
+pub async fn start_legendary_stream(
+    app: &AppHandle,
+    config_path: &str,
+    stream_id: &str,
+    operation: &LegendaryOperation,
+    download_size: Option<u64>,
+    log: bool,
+) -> Result<String, String> {
+    let args = operation_args(operation)?;
+    spawn_stream(app, config_path, stream_id, &args, download_size, log, None)?;
 
     Ok(stream_id.to_string())
 }
 
-pub async fn stop_legendary_stream(stream_id: &str, force_kill_all: bool) -> Result<bool, String> {
-    if force_kill_all {
-        kill_legendary_processes();
-        Ok(true)
-    } else {
-        let child = {
-            let mut streams = ACTIVE_STREAMS.lock().unwrap();
-            streams.remove(stream_id)
-        };
+/// Starts a stream like [`start_legendary_stream`] and also forwards every event it emits
+/// to the returned receiver, for Rust-side consumers such as the download queue.
+pub fn start_observed_stream(
+    app: &AppHandle,
+    config_path: &str,
+    stream_id: &str,
+    operation: &LegendaryOperation,
+    download_size: Option<u64>,
+    log: bool,
+) -> Result<UnboundedReceiver<StreamEvent>, String> {
+    let args = operation_args(operation)?;
+    let (tx, rx) = unbounded_channel();
+
+    spawn_stream(
+        app,
+        config_path,
+        stream_id,
+        &args,
+        download_size,
+        log,
+        Some(tx),
+    )?;
+
+    Ok(rx)
+}
+
+/// Runs an operation as an observed stream and waits for it, so the frontend can follow the
+/// output while the caller gets the same result as [`run_legendary`].
+pub async fn run_observed_stream(
+    app: &AppHandle,
+    config_path: &str,
+    stream_id: &str,
+    operation: &LegendaryOperation,
+) -> Result<CommandOutput, LegendaryError> {
+    let mut events = start_observed_stream(app, config_path, stream_id, operation, None, true)
+        .map_err(|message| LegendaryError::Process { message })?;
+
+    let mut stdout = String::new();
+    let mut stderr = String::new();
 
-        if let Some(child) = child {
-            match child.kill() {
-                Ok(_) => Ok(true),
-                Err(e) => Err(format!("Failed to kill process: {}", e)),
+    while let Some(event) = events.recv().await {
+        match event.event_type {
+            EventType::Stdout => {
+                stdout.push_str(&event.data);
+                stdout.push('\n');
             }
-        } else {
-            Ok(false)
+            EventType::Stderr => {
+                stderr.push_str(&event.data);
+                stderr.push('\n');
+            }
+            EventType::Terminated => {
+                if matches!(
+                    event.termination,
+                    Some(
+                        TerminationReason::Cancelled
+                            | TerminationReason::Interrupted
+                            | TerminationReason::Killed
+                    )
+                ) {
+                    return Err(LegendaryError::Cancelled);
+                }
+
+                return Ok(CommandOutput {
+                    code: event.code,
+                    signal: event.signal,
+                    error: event.reason,
+                    stdout: stdout.trim().to_string(),
+                    stderr: stderr.trim().to_string(),
+                });
+            }
+            EventType::Error => {
+                return Err(LegendaryError::Process {
+                    message: event.data,
+                })
+            }
+            _ => continue,
         }
     }
+
+    Err(LegendaryError::Process {
+        message: format!("Stream {} ended without terminating", stream_id),
+    })
 }
 
-fn create_legendary_sidecar(
+fn spawn_stream(
     app: &AppHandle,
     config_path: &str,
+    stream_id: &str,
     args: &[String],
-) -> Result<tauri_plugin_shell::process::Command, String> {
-    Ok(app
-        .shell()
-        .sidecar("legendary")
-        .map_err(|e| e.to_string())?
-        .args(args)
-        .env("LEGENDARY_CONFIG_PATH", config_path))
-}
+    download_size: Option<u64>,
+    log: bool,
+    observer: Option<UnboundedSender<StreamEvent>>,
+) -> Result<(), String> {
+    let sidecar = create_legendary_sidecar(app, config_path, args)?.set_raw_out(true);
 
-pub fn kill_legendary_processes() {
+    let mut streams = ACTIVE_STREAMS.lock().unwrap();
+
+    // Replacing a live stream would orphan its process, only finished ids can be reused
+    if streams
+        .get(stream_id)
+        .is_some_and(|stream| stream.state != StreamState::Finished)
     {
-        let streams = ACTIVE_STREAMS.lock().unwrap();
-        if streams.is_empty() {
+        return Err(format!("Stream {} is already active", stream_id));
+    }
+
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    QUEUED_STREAMS
+        .lock()
+        .unwrap()
+        .insert(stream_id.to_string(), cancel_tx);
+
+    streams.insert(
+        stream_id.to_string(),
+        ActiveStream {
+            child: StreamChild::default(),
+            args: args.to_vec(),
+            state: StreamState::Queued,
+            pid: None,
+            created_at: now_ms(),
+            started_at: None,
+            output_bytes: 0,
+            last_progress: None,
+            backlog: VecDeque::new(),
+            next_seq: 1,
+        },
+    );
+    drop(streams);
+
+    let mut emitter = StreamEmitter::new(
+        app.clone(),
+        stream_id,
+        ProgressParser::with_total(download_size.unwrap_or_default()),
+        observer,
+        log.then(|| LogWriter::create(app, args).ok()).flatten(),
+    );
+
+    let app = app.clone();
+    let config_path = config_path.to_string();
+    let args = args.to_vec();
+
+    tauri::async_runtime::spawn(async move {
+        let slot = tokio::select! {
+            slot = acquire_slot(&app, &config_path, &emitter.stream_id, &args) => Some(slot),
+            _ = cancel_rx => None,
+        };
+
+        let Some(_slot) = slot else {
+            QUEUED_STREAMS.lock().unwrap().remove(&emitter.stream_id);
+
+            emitter.send(StreamEvent {
+                termination: Some(TerminationReason::Cancelled),
+                ..emitter.event(EventType::Terminated)
+            });
+            finish_stream(&emitter.stream_id);
             return;
+        };
+
+        let (mut rx, child) = match sidecar.spawn() {
+            Ok(spawned) => spawned,
+            Err(e) => {
+                QUEUED_STREAMS.lock().unwrap().remove(&emitter.stream_id);
+                emitter.send(StreamEvent {
+                    data: e.to_string(),
+                    ..emitter.event(EventType::Error)
+                });
+                finish_stream(&emitter.stream_id);
+                return;
+            }
+        };
+
+        if let Some(stream) = ACTIVE_STREAMS.lock().unwrap().get_mut(&emitter.stream_id) {
+            stream.state = StreamState::Running;
+            stream.pid = Some(child.pid());
+            stream.started_at = Some(now_ms());
+            *stream.child.lock().unwrap() = Some(child);
         }
-    }
 
-    let _ = Command::new("taskkill")
-        .args(&["/F", "/IM", "legendary.exe"])
-        .creation_flags(0x08000000)
-        .output();
+        // The stream stays in the queue until its child is registered, a stop that came in
+        // meanwhile took the entry and is carried out here
+        let stopped = QUEUED_STREAMS
+            .lock()
+            .unwrap()
+            .remove(&emitter.stream_id)
+            .is_none();
 
-    {
-        let mut streams = ACTIVE_STREAMS.lock().unwrap();
-        streams.clear();
+        if stopped {
+            set_stop_reason(&emitter.stream_id, TerminationReason::Cancelled);
+
+            if let Some(child) = take_stream_child(&emitter.stream_id) {
+                kill_descendants(child.pid());
+                let _ = child.kill();
+            }
+        }
+
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(bytes) => emitter.push(EventType::Stdout, &bytes),
+                CommandEvent::Stderr(bytes) => emitter.push(EventType::Stderr, &bytes),
+                CommandEvent::Terminated(payload) => {
+                    emitter.flush();
+                    take_stream_child(&emitter.stream_id);
+
+                    let termination = STOP_REASONS
+                        .lock()
+                        .unwrap()
+                        .remove(&emitter.stream_id)
+                        .unwrap_or(TerminationReason::Exited);
+
+                    emitter.send(StreamEvent {
+                        code: payload.code,
+                        signal: payload.signal,
+                        termination: Some(termination),
+                        reason: classify_failure(payload.code, &emitter.stderr_tail),
+                        ..emitter.event(EventType::Terminated)
+                    });
+                    finish_stream(&emitter.stream_id);
+                    break;
+                }
+                CommandEvent::Error(error) => {
+                    emitter.flush();
+                    take_stream_child(&emitter.stream_id);
+
+                    STOP_REASONS.lock().unwrap().remove(&emitter.stream_id);
+
+                    emitter.send(StreamEvent {
+                        data: error,
+                        ..emitter.event(EventType::Error)
+                    });
+                    finish_stream(&emitter.stream_id);
+                    break;
+                }
+                _ => continue,
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Returns the events of a stream after `since_seq`, or its whole backlog. Streams stay
+/// available for a while after they finished.
+pub fn get_stream_log(stream_id: &str, since_seq: Option<u64>) -> Result<Vec<StreamEvent>, String> {
+    let streams = ACTIVE_STREAMS.lock().unwrap();
+    let stream = streams
+        .get(stream_id)
+        .ok_or_else(|| format!("Stream '{}' not found", stream_id))?;
+
+    let since_seq = since_seq.unwrap_or_default();
+
+    Ok(stream
+        .backlog
+        .iter()
+        .filter(|event| event.seq > since_seq)
+        .cloned()
+        .collect())
+}
+
+/// Describes every stream that is queued, running or recently finished.
+pub fn list_streams() -> Vec<StreamInfo> {
+    let streams = ACTIVE_STREAMS.lock().unwrap();
+
+    let mut list: Vec<StreamInfo> = streams
+        .iter()
+        .map(|(stream_id, stream)| StreamInfo {
+            stream_id: stream_id.clone(),
+            args: stream.args.clone(),
+            state: stream.state,
+            pid: stream.pid,
+            created_at: stream.created_at,
+            started_at: stream.started_at,
+            output_bytes: stream.output_bytes,
+            last_progress: stream.last_progress.clone(),
+            last_seq: stream.next_seq - 1,
+        })
+        .collect();
+
+    list.sort_by_key(|stream| stream.created_at);
+    list
+}
+
+/// Writes to the stdin of a running stream, e.g. to answer a `Prompt` event. The data is
+/// sent as is, so answers need their own trailing newline.
+pub fn write_stream(stream_id: &str, data: &str) -> Result<(), String> {
+    let not_running = || format!("Stream '{}' is not running", stream_id);
+
+    let child = stream_child(stream_id).ok_or_else(not_running)?;
+    let mut child = child.lock().unwrap();
+
+    child
+        .as_mut()
+        .ok_or_else(not_running)?
+        .write(data.as_bytes())
+        .map_err(|e| format!("Failed to write to stream: {}", e))
+}
+
+/// Clones the stream's child handle, so it is used after `ACTIVE_STREAMS` is unlocked.
+fn stream_child(stream_id: &str) -> Option<StreamChild> {
+    let streams = ACTIVE_STREAMS.lock().unwrap();
+    streams
+        .get(stream_id)
+        .map(|stream| Arc::clone(&stream.child))
+}
+
+fn take_stream_child(stream_id: &str) -> Option<CommandChild> {
+    stream_child(stream_id)?.lock().unwrap().take()
+}
+
+fn is_stream_running(stream_id: &str) -> bool {
+    stream_child(stream_id).is_some_and(|child| child.lock().unwrap().is_some())
+}
+
+/// Keeps the backlog of a finished stream until newer streams push it out.
+fn finish_stream(stream_id: &str) {
+    let mut streams = ACTIVE_STREAMS.lock().unwrap();
+    let mut finished = FINISHED_STREAMS.lock().unwrap();
+
+    if let Some(stream) = streams.get_mut(stream_id) {
+        stream.state = StreamState::Finished;
+    }
+
+    finished.push_back(stream_id.to_string());
+
+    while finished.len() > FINISHED_STREAMS_KEPT {
+        let Some(oldest) = finished.pop_front() else {
+            break;
+        };
+
+        // The id may have been reused by a stream that is still queued or running
+        if streams
+            .get(&oldest)
+            .is_some_and(|stream| stream.state == StreamState::Finished)
+        {
+            streams.remove(&oldest);
+        }
+    }
+}
+
+/// Frames the raw output of a stream into clean lines and emits them, along with
+/// any progress parsed from them, as `legendary_stream:{id}` events.
+struct StreamEmitter {
+    app: AppHandle,
+    stream_id: String,
+    event_name: String,
+    progress_parser: ProgressParser,
+    stdout_framer: LineFramer,
+    stderr_framer: LineFramer,
+    stderr_tail: String,
+    observer: Option<UnboundedSender<StreamEvent>>,
+    log: Option<LogWriter>,
+}
+
+impl StreamEmitter {
+    fn new(
+        app: AppHandle,
+        stream_id: &str,
+        progress_parser: ProgressParser,
+        observer: Option<UnboundedSender<StreamEvent>>,
+        log: Option<LogWriter>,
+    ) -> Self {
+        Self {
+            app,
+            stream_id: stream_id.to_string(),
+            event_name: format!("legendary_stream:{}", stream_id),
+            progress_parser,
+            stdout_framer: LineFramer::default(),
+            stderr_framer: LineFramer::default(),
+            stderr_tail: String::new(),
+            observer,
+            log,
+        }
+    }
+
+    fn event(&self, event_type: EventType) -> StreamEvent {
+        StreamEvent {
+            stream_id: self.stream_id.clone(),
+            seq: 0,
+            event_type,
+            data: String::new(),
+            code: None,
+            signal: None,
+            progress: None,
+            termination: None,
+            reason: None,
+        }
+    }
+
+    fn send(&mut self, mut event: StreamEvent) {
+        if let Some(log) = &mut self.log {
+            log_event(log, &event);
+        }
+
+        if let Some(stream) = ACTIVE_STREAMS.lock().unwrap().get_mut(&self.stream_id) {
+            event.seq = stream.next_seq;
+            stream.next_seq += 1;
+
+            if event.progress.is_some() {
+                stream.last_progress = event.progress.clone();
+            }
+
+            if stream.backlog.len() == STREAM_BACKLOG_SIZE {
+                stream.backlog.pop_front();
+            }
+
+            stream.backlog.push_back(event.clone());
+        }
+
+        let _ = self.app.emit(&self.event_name, &event);
+
+        // Also sent globally, the dialog answering prompts can't know every stream id
+        if matches!(event.event_type, EventType::Prompt) {
+            let _ = self.app.emit("legendary_prompt", &event);
+        }
+
+        if let Some(observer) = &self.observer {
+            let _ = observer.send(event);
+        }
+    }
+
+    fn push(&mut self, event_type: EventType, bytes: &[u8]) {
+        if let Some(stream) = ACTIVE_STREAMS.lock().unwrap().get_mut(&self.stream_id) {
+            stream.output_bytes += bytes.len() as u64;
+        }
+
+        let lines = match event_type {
+            EventType::Stderr => self.stderr_framer.push(bytes),
+            _ => self.stdout_framer.push(bytes),
+        };
+
+        for line in lines {
+            self.line(event_type.clone(), line);
+        }
+
+        let prompt = match event_type {
+            EventType::Stderr => self.stderr_framer.take_prompt(),
+            _ => self.stdout_framer.take_prompt(),
+        };
+
+        if let Some(prompt) = prompt {
+            self.send(StreamEvent {
+                data: prompt,
+                ..self.event(EventType::Prompt)
+            });
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(line) = self.stdout_framer.flush() {
+            self.line(EventType::Stdout, line);
+        }
+
+        if let Some(line) = self.stderr_framer.flush() {
+            self.line(EventType::Stderr, line);
+        }
+    }
+
+    fn line(&mut self, event_type: EventType, line: String) {
+        let progress = self.progress_parser.parse_output(&line);
+
+        if matches!(event_type, EventType::Stderr) {
+            self.stderr_tail.push_str(&line);
+            self.stderr_tail.push('\n');
+
+            if self.stderr_tail.len() > STDERR_TAIL_SIZE {
+                let mut cut = self.stderr_tail.len() - STDERR_TAIL_SIZE;
+                while !self.stderr_tail.is_char_boundary(cut) {
+                    cut += 1;
+                }
+                self.stderr_tail.drain(..cut);
+            }
+        }
+
+        self.send(StreamEvent {
+            data: line,
+            ..self.event(event_type)
+        });
+
+        if let Some(progress) = progress {
+            self.send(StreamEvent {
+                progress: Some(progress),
+                ..self.event(EventType::Progress)
+            });
+        }
+    }
+}
+
+fn log_event(log: &mut LogWriter, event: &StreamEvent) {
+    match event.event_type {
+        EventType::Stdout | EventType::Stderr | EventType::Prompt => log.write_line(&event.data),
+        EventType::Progress => {}
+        EventType::Terminated => log.write_line(&format!(
+            "Exited with code {:?} ({:?})",
+            event.code,
+            event.termination.unwrap_or(TerminationReason::Exited)
+        )),
+        EventType::Error => log.write_line(&format!("Error: {}", event.data)),
+    }
+}
+
+/// Buffers partial output and splits it into lines on `\n` and `\r`, so progress
+/// bars redrawn with carriage returns come out as separate lines.
+#[derive(Debug, Default)]
+pub struct LineFramer {
+    buffer: Vec<u8>,
+}
+
+impl LineFramer {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for &byte in bytes {
+            if byte == b'\n' || byte == b'\r' {
+                lines.extend(self.take_line());
+            } else {
+                self.buffer.push(byte);
+            }
+        }
+
+        lines
+    }
+
+    pub fn flush(&mut self) -> Option<String> {
+        self.take_line()
+    }
+
+    /// Takes the unterminated line if legendary is waiting for input on it, since
+    /// `input()` prompts never end with a newline.
+    pub fn take_prompt(&mut self) -> Option<String> {
+        let pending = strip_ansi(&String::from_utf8_lossy(&self.buffer));
+
+        if !is_prompt(&pending) {
+            return None;
+        }
+
+        self.buffer.clear();
+        Some(pending.trim().to_string())
+    }
+
+    fn take_line(&mut self) -> Option<String> {
+        let line = strip_ansi(&String::from_utf8_lossy(&self.buffer));
+        self.buffer.clear();
+
+        let line = line.trim_end();
+        (!line.is_empty()).then(|| line.to_string())
+    }
+}
+
+/// Matches legendary's prompts: yes/no and numbered choices (`[Y/n]: `, `[1-3]: `),
+/// `[Enter to confirm]: ` and the `Please enter ...: ` questions.
+fn is_prompt(line: &str) -> bool {
+    let line = line.trim_start();
+
+    line.ends_with("]: ") || (line.starts_with("Please enter") && line.ends_with(": "))
+}
+
+/// Removes terminal escape sequences (colors, cursor movement, titles) and other
+/// control characters from a line.
+pub fn strip_ansi(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            if !c.is_control() || c == '\t' {
+                result.push(c);
+            }
+            continue;
+        }
+
+        match chars.next() {
+            // CSI: parameters and intermediates until a final byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    result
+}
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// Keeps a running progress snapshot built from legendary's download status lines.
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    progress: DownloadProgress,
+    current_download_size: u64,
+}
+
+impl ProgressParser {
+    /// Legendary only reports the remaining size when resuming, so the full size
+    /// has to be carried over from the previous run to keep the percentage right.
+    pub fn with_total(total: u64) -> Self {
+        Self {
+            progress: DownloadProgress {
+                total,
+                ..Default::default()
+            },
+            current_download_size: 0,
+        }
+    }
+
+    pub fn progress(&self) -> &DownloadProgress {
+        &self.progress
+    }
+
+    /// Returns the updated snapshot if any line of `output` changed it.
+    pub fn parse_output(&mut self, output: &str) -> Option<DownloadProgress> {
+        let mut changed = false;
+
+        for line in output.lines() {
+            changed |= self.parse_line(line);
+        }
+
+        changed.then(|| self.progress.clone())
+    }
+
+    fn parse_line(&mut self, line: &str) -> bool {
+        if let Some(size) = mib_after(line, "Download size:") {
+            self.current_download_size = size;
+            if self.progress.total == 0 {
+                self.progress.total = size;
+            }
+
+            return true;
+        }
+
+        if let Some(eta_ms) = eta_after(line) {
+            self.progress.eta_ms = eta_ms;
+            return true;
+        }
+
+        if let Some(downloaded) = mib_after(line, "Downloaded:") {
+            let total = self.progress.total;
+            let downloaded = (downloaded + total).saturating_sub(self.current_download_size);
+
+            self.progress.downloaded = downloaded;
+            self.progress.percent = if total > 0 {
+                downloaded as f64 / total as f64 * 100.0
+            } else {
+                0.0
+            };
+
+            return true;
+        }
+
+        if let Some(speed) = rate_after(line, "Download", " MiB/s (raw)") {
+            self.progress.download_speed = speed;
+            return true;
+        }
+
+        if let Some(speed) = rate_after(line, "Disk", " MiB/s (write)") {
+            self.progress.disk_write_speed = speed;
+            return true;
+        }
+
+        false
+    }
+}
+
+/// Parses lines like `Downloaded: 9.87 MiB`.
+fn mib_after(line: &str, label: &str) -> Option<u64> {
+    let rest = &line[line.find(label)? + label.len()..];
+    let (value, rest) = split_number(rest.trim_start())?;
+
+    rest.starts_with(" MiB").then(|| mib_to_bytes(value))
+}
+
+/// Parses lines like `+ Download\t- 1.97 MiB/s (raw) / 2.54 MiB/s (decompressed)`.
+fn rate_after(line: &str, label: &str, suffix: &str) -> Option<u64> {
+    let rest = &line[line.find(label)? + label.len()..];
+    let rest = rest.trim_start().strip_prefix("- ")?;
+    let (value, rest) = split_number(rest)?;
+
+    rest.starts_with(suffix).then(|| mib_to_bytes(value))
+}
+
+/// Parses the `ETA: 00:13:25` part of a progress line into milliseconds.
+fn eta_after(line: &str) -> Option<u64> {
+    let rest = &line[line.find("ETA:")? + "ETA:".len()..];
+    let eta = rest.split_whitespace().next()?;
+
+    let parts: Vec<u64> = eta
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+
+    match parts.as_slice() {
+        [hours, minutes, seconds] => Some((hours * 3600 + minutes * 60 + seconds) * 1000),
+        _ => None,
+    }
+}
+
+fn split_number(text: &str) -> Option<(f64, &str)> {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+
+    let value = text[..end].parse().ok()?;
+
+    Some((value, &text[end..]))
+}
+
+fn mib_to_bytes(mib: f64) -> u64 {
+    (mib * MIB) as u64
+}
+
+/// Interrupts the stream like Ctrl-C would so legendary can save its progress, and only
+/// kills it if it is still running once `grace_period_ms` has passed.
+pub async fn stop_legendary_stream(
+    stream_id: &str,
+    force_kill_all: bool,
+    grace_period_ms: Option<u64>,
+) -> Result<bool, String> {
+    if force_kill_all {
+        for (_, cancel) in QUEUED_STREAMS.lock().unwrap().drain() {
+            let _ = cancel.send(());
+        }
+
+        {
+            let streams = ACTIVE_STREAMS.lock().unwrap();
+            let mut reasons = STOP_REASONS.lock().unwrap();
+
+            for (stream_id, stream) in streams.iter() {
+                if stream.state == StreamState::Running {
+                    reasons.insert(stream_id.clone(), TerminationReason::Killed);
+                }
+            }
+        }
+
+        kill_legendary_processes();
+        return Ok(true);
+    }
+
+    let queued = QUEUED_STREAMS.lock().unwrap().remove(stream_id);
+
+    // Taking the entry stops the stream even when it is about to start its child
+    if let Some(cancel) = queued {
+        let _ = cancel.send(());
+        return Ok(true);
+    }
+
+    let pid = stream_child(stream_id)
+        .and_then(|child| child.lock().unwrap().as_ref().map(|child| child.pid()));
+
+    let Some(pid) = pid else {
+        return Ok(false);
+    };
+
+    let grace_period = Duration::from_millis(grace_period_ms.unwrap_or(DEFAULT_GRACE_PERIOD_MS));
+
+    // Recorded first since the stream can exit as soon as it is interrupted
+    set_stop_reason(stream_id, TerminationReason::Interrupted);
+
+    if !grace_period.is_zero() {
+        // The console functions are process wide, so stops on Windows take turns
+        #[cfg(windows)]
+        let _console = CONSOLE_LOCK.lock().await;
+
+        let exited = interrupt_process(pid) && wait_for_stream_exit(stream_id, grace_period).await;
+
+        #[cfg(windows)]
+        restore_ctrl_c_handler();
+
+        if exited {
+            return Ok(true);
+        }
+    }
+
+    let Some(child) = take_stream_child(stream_id) else {
+        return Ok(true);
+    };
+
+    set_stop_reason(stream_id, TerminationReason::Killed);
+    kill_descendants(child.pid());
+
+    match child.kill() {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("Failed to kill process: {}", e)),
+    }
+}
+
+fn set_stop_reason(stream_id: &str, reason: TerminationReason) {
+    let mut reasons = STOP_REASONS.lock().unwrap();
+    reasons.insert(stream_id.to_string(), reason);
+}
+
+async fn wait_for_stream_exit(stream_id: &str, timeout: Duration) -> bool {
+    let poll_interval = Duration::from_millis(100);
+    let mut waited = Duration::ZERO;
+
+    while waited < timeout {
+        if !is_stream_running(stream_id) {
+            return true;
+        }
+
+        tokio::time::sleep(poll_interval).await;
+        waited += poll_interval;
+    }
+
+    !is_stream_running(stream_id)
+}
+
+/// Sends SIGINT to the root only. The PyInstaller bootloader forwards it to legendary,
+/// signalling the whole tree would deliver it twice and cut its shutdown short.
+#[cfg(not(windows))]
+fn interrupt_process(root_pid: u32) -> bool {
+    use sysinfo::Signal;
+
+    let pid = Pid::from_u32(root_pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+
+    system
+        .process(pid)
+        .and_then(|process| process.kill_with(Signal::Interrupt))
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+static CONSOLE_LOCK: LazyLock<tokio::sync::Mutex<()>> =
+    LazyLock::new(|| tokio::sync::Mutex::new(()));
+
+/// Windows has no signals, so attach to the hidden console of the process and raise
+/// Ctrl-C there, which reaches every process sharing that console.
+#[cfg(windows)]
+fn interrupt_process(root_pid: u32) -> bool {
+    use windows_sys::Win32::System::Console::{
+        AttachConsole, FreeConsole, GenerateConsoleCtrlEvent, SetConsoleCtrlHandler,
+        ATTACH_PARENT_PROCESS, CTRL_C_EVENT,
+    };
+
+    unsafe {
+        FreeConsole();
+
+        if AttachConsole(root_pid) == 0 {
+            AttachConsole(ATTACH_PARENT_PROCESS);
+            return false;
+        }
+
+        // Keep the launcher itself from handling the Ctrl-C it is about to raise
+        SetConsoleCtrlHandler(None, 1);
+        let sent = GenerateConsoleCtrlEvent(CTRL_C_EVENT, 0) != 0;
+
+        FreeConsole();
+        AttachConsole(ATTACH_PARENT_PROCESS);
+
+        sent
+    }
+}
+
+#[cfg(windows)]
+fn restore_ctrl_c_handler() {
+    use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
+
+    unsafe {
+        SetConsoleCtrlHandler(None, 0);
+    }
+}
+
+/// Read-only commands can share a config directory, up to this many at a time.
+const MAX_CONCURRENT_READS: usize = 4;
+
+/// Commands that only read legendary's data. `list` and `status` are missing on purpose,
+/// they refresh the session and write user.json.
+const READ_ONLY_COMMANDS: &[&str] = &["list-installed", "list-files", "list-saves", "info"];
+
+/// Commands that take legendary's installed data lock, legendary refuses to run two of
+/// them at a time. Anything that is neither is treated as mutating.
+const INSTALLING_COMMANDS: &[&str] = &[
+    "install",
+    "download",
+    "update",
+    "repair",
+    "import",
+    "move",
+    "uninstall",
+    "verify",
+    "egl-sync",
+    "eos-overlay",
+];
+
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(0);
+static NEXT_SLOT: AtomicU64 = AtomicU64::new(0);
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+static SCHEDULERS: LazyLock<Mutex<HashMap<String, ConfigScheduler>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Queues legendary invocations that share a config directory, since legendary's lock
+/// files make a second mutating instance fail instead of waiting.
+#[derive(Default)]
+struct ConfigScheduler {
+    running: Vec<ScheduledCommand>,
+    waiting: VecDeque<(ScheduledCommand, oneshot::Sender<()>)>,
+}
+
+impl ConfigScheduler {
+    fn can_start(&self, access: CommandAccess) -> bool {
+        let reads = self
+            .running
+            .iter()
+            .filter(|command| command.access == CommandAccess::ReadOnly)
+            .count();
+
+        if access == CommandAccess::ReadOnly && reads >= MAX_CONCURRENT_READS {
+            return false;
+        }
+
+        self.running
+            .iter()
+            .all(|command| !access.conflicts_with(command.access))
+    }
+
+    /// Whether a new command can start right away. It may not overtake a waiting command
+    /// it conflicts with, so a stream of reads can't starve a write.
+    fn can_start_now(&self, access: CommandAccess) -> bool {
+        self.can_start(access)
+            && self
+                .waiting
+                .iter()
+                .all(|(command, _)| !access.conflicts_with(command.access))
+    }
+
+    /// Starts every waiting command that no longer conflicts with the running ones or with
+    /// a command waiting ahead of it.
+    fn start_waiting(&mut self) {
+        let mut blocked = Vec::new();
+        let mut index = 0;
+
+        while index < self.waiting.len() {
+            let access = self.waiting[index].0.access;
+            let overtakes = blocked
+                .iter()
+                .any(|waiting: &CommandAccess| access.conflicts_with(*waiting));
+
+            if overtakes || !self.can_start(access) {
+                blocked.push(access);
+                index += 1;
+                continue;
+            }
+
+            let (command, start) = self.waiting.remove(index).unwrap();
+
+            if start.send(()).is_ok() {
+                self.running.push(command);
+            }
+        }
+    }
+
+    fn event(&self, config_path: &str) -> SchedulerEvent {
+        SchedulerEvent {
+            config_path: config_path.to_string(),
+            running: self.running.clone(),
+            waiting: self
+                .waiting
+                .iter()
+                .map(|(command, _)| command.clone())
+                .collect(),
+        }
+    }
+}
+
+/// A running command's place in the scheduler, released when dropped.
+struct SchedulerSlot {
+    app: AppHandle,
+    config_path: String,
+    slot: u64,
+}
+
+impl Drop for SchedulerSlot {
+    fn drop(&mut self) {
+        let mut schedulers = SCHEDULERS.lock().unwrap();
+
+        let Some(scheduler) = schedulers.get_mut(&self.config_path) else {
+            return;
+        };
+
+        scheduler
+            .running
+            .retain(|command| command.slot != self.slot);
+        scheduler
+            .waiting
+            .retain(|(command, _)| command.slot != self.slot);
+        scheduler.start_waiting();
+
+        let _ = self.app.emit(
+            "legendary_scheduler_changed",
+            &scheduler.event(&self.config_path),
+        );
+
+        if scheduler.running.is_empty() && scheduler.waiting.is_empty() {
+            schedulers.remove(&self.config_path);
+        }
+    }
+}
+
+/// Waits until the command can run without conflicting with the others using the same
+/// config directory. Every change is emitted as `legendary_scheduler_changed`.
+async fn acquire_slot(
+    app: &AppHandle,
+    config_path: &str,
+    id: &str,
+    args: &[String],
+) -> SchedulerSlot {
+    let command = ScheduledCommand {
+        id: id.to_string(),
+        args: args.to_vec(),
+        access: command_access(args),
+        slot: NEXT_SLOT.fetch_add(1, Ordering::Relaxed),
+    };
+    let slot = command.slot;
+
+    let start = {
+        let mut schedulers = SCHEDULERS.lock().unwrap();
+        let scheduler = schedulers.entry(config_path.to_string()).or_default();
+
+        let start = if scheduler.can_start_now(command.access) {
+            scheduler.running.push(command);
+            None
+        } else {
+            let (tx, rx) = oneshot::channel();
+            scheduler.waiting.push_back((command, tx));
+            Some(rx)
+        };
+
+        let _ = app.emit("legendary_scheduler_changed", &scheduler.event(config_path));
+
+        start
+    };
+
+    // Created before waiting so a dropped future still gives up its place in the queue
+    let slot = SchedulerSlot {
+        app: app.clone(),
+        config_path: config_path.to_string(),
+        slot,
+    };
+
+    if let Some(start) = start {
+        let _ = start.await;
+    }
+
+    slot
+}
+
+/// Whether a command with `access` would have to wait for others using the config directory.
+pub fn is_scheduler_busy(config_path: &str, access: CommandAccess) -> bool {
+    SCHEDULERS
+        .lock()
+        .unwrap()
+        .get(config_path)
+        .is_some_and(|scheduler| !scheduler.can_start_now(access))
+}
+
+fn command_access(args: &[String]) -> CommandAccess {
+    let Some(command) = args.iter().find(|arg| !arg.starts_with('-')) else {
+        return CommandAccess::ReadOnly;
+    };
+
+    if READ_ONLY_COMMANDS.contains(&command.as_str()) {
+        CommandAccess::ReadOnly
+    } else if INSTALLING_COMMANDS.contains(&command.as_str()) {
+        CommandAccess::Installing
+    } else {
+        CommandAccess::Mutating
+    }
+}
+
+/// Matches the config directory the frontend passes in `Legendary.getConfigPath`.
+pub fn default_config_path(app: &AppHandle) -> Result<String, String> {
+    let folder = if cfg!(debug_assertions) {
+        "legendary-dev"
+    } else {
+        "legendary"
+    };
+
+    Ok(storage::data_directory(app)?
+        .join(folder)
+        .to_string_lossy()
+        .to_string())
+}
+
+/// Reads the id of the account legendary is logged in with, if any.
+pub fn get_account_id(config_path: &str) -> Option<String> {
+    let content = fs::read_to_string(Path::new(config_path).join("user.json")).ok()?;
+    let user: serde_json::Value = serde_json::from_str(&content).ok()?;
+
+    user.get("account_id")?.as_str().map(|id| id.to_string())
+}
+
+fn create_legendary_sidecar(
+    app: &AppHandle,
+    config_path: &str,
+    args: &[String],
+) -> Result<Command, String> {
+    Ok(legendary_command(app)?
+        .args(args)
+        .env("LEGENDARY_CONFIG_PATH", config_path))
+}
+
+/// Prefers the bundled sidecar and falls back to a `legendary` on `PATH`
+/// for Linux builds that are packaged without one.
+fn legendary_command(app: &AppHandle) -> Result<Command, String> {
+    let shell = app.shell();
+
+    #[cfg(not(windows))]
+    {
+        let has_sidecar = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join("legendary")))
+            .is_some_and(|path| path.exists());
+
+        if !has_sidecar {
+            return Ok(shell.command("legendary"));
+        }
+    }
+
+    shell.sidecar("legendary").map_err(|e| e.to_string())
+}
+
+/// Stops every stream through the grace period before the app exits, then kills what is
+/// left, including the `run_legendary` processes.
+pub async fn stop_all_streams() {
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+
+    let stream_ids: Vec<String> = ACTIVE_STREAMS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, stream)| stream.state != StreamState::Finished)
+        .map(|(stream_id, _)| stream_id.clone())
+        .collect();
+
+    let stops: Vec<_> = stream_ids
+        .into_iter()
+        .map(|stream_id| {
+            tauri::async_runtime::spawn(async move {
+                let _ = stop_legendary_stream(&stream_id, false, None).await;
+            })
+        })
+        .collect();
+
+    for stop in stops {
+        let _ = stop.await;
+    }
+
+    kill_legendary_processes();
+}
+
+/// Set once the app is exiting, streams ending from then on were stopped by the shutdown.
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+/// Kills every legendary process the launcher started, leaving instances run from a
+/// terminal or other launchers alone.
+pub fn kill_legendary_processes() {
+    let children: Vec<StreamChild> = {
+        let streams = ACTIVE_STREAMS.lock().unwrap();
+        streams
+            .values()
+            .map(|stream| Arc::clone(&stream.child))
+            .collect()
+    };
+
+    let mut pids: Vec<u32> = children
+        .iter()
+        .filter_map(|child| child.lock().unwrap().take())
+        .map(|child| child.pid())
+        .collect();
+
+    pids.extend(RUNNING_COMMANDS.lock().unwrap().drain());
+
+    if pids.is_empty() {
+        return;
+    }
+
+    kill_process_tree(&pids);
+}
+
+/// The Windows sidecar is a PyInstaller bootloader that spawns the real legendary
+/// process, so descendants have to be killed along with the root.
+fn kill_process_tree(root_pids: &[u32]) {
+    let system = process_snapshot();
+
+    for pid in process_tree(&system, root_pids).iter().rev() {
+        if let Some(process) = system.process(*pid) {
+            process.kill();
+        }
+    }
+}
+
+fn kill_descendants(root_pid: u32) {
+    let system = process_snapshot();
+
+    for pid in process_tree(&system, &[root_pid]).iter().skip(1).rev() {
+        if let Some(process) = system.process(*pid) {
+            process.kill();
+        }
+    }
+}
+
+fn process_snapshot() -> System {
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing().without_tasks(),
+    );
+
+    system
+}
+
+/// Returns the given roots followed by all of their descendants, parents before children.
+fn process_tree(system: &System, root_pids: &[u32]) -> Vec<Pid> {
+    let mut pids: Vec<Pid> = root_pids.iter().map(|pid| Pid::from_u32(*pid)).collect();
+    let mut index = 0;
+
+    while index < pids.len() {
+        let parent = pids[index];
+        let children: Vec<Pid> = system
+            .processes()
+            .iter()
+            .filter(|(pid, process)| process.parent() == Some(parent) && !pids.contains(pid))
+            .map(|(pid, _)| *pid)
+            .collect();
+
+        pids.extend(children);
+        index += 1;
+    }
+
+    pids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Captured from `legendary install` (DLManager status report).
+    const STATUS_REPORT: &str = "\
+[cli] INFO: Install size: 28545.33 MiB
+[cli] INFO: Download size: 26245.33 MiB (Compression savings: 8.1%)
+[DLManager] INFO: = Progress: 0.29% (55/19015), Running for 00:00:05, ETA: 00:28:30
+[DLManager] INFO:  - Downloaded: 60.07 MiB, Written: 60.82 MiB
+[DLManager] INFO:  - Cache usage: 15.00 MiB, active tasks: 32
+[DLManager] INFO:  + Download\t- 12.01 MiB/s (raw) / 12.16 MiB/s (decompressed)
+[DLManager] INFO:  + Disk\t- 12.16 MiB/s (write) / 0.00 MiB/s (read)
+";
+
+    fn expected() -> DownloadProgress {
+        let total = mib_to_bytes(26245.33);
+        let downloaded = mib_to_bytes(60.07);
+
+        DownloadProgress {
+            downloaded,
+            total,
+            percent: downloaded as f64 / total as f64 * 100.0,
+            eta_ms: (28 * 60 + 30) * 1000,
+            download_speed: mib_to_bytes(12.01),
+            disk_write_speed: mib_to_bytes(12.16),
+        }
+    }
+
+    fn assert_progress(actual: &DownloadProgress, expected: &DownloadProgress) {
+        assert_eq!(actual.downloaded, expected.downloaded);
+        assert_eq!(actual.total, expected.total);
+        assert!((actual.percent - expected.percent).abs() < 1e-9);
+        assert_eq!(actual.eta_ms, expected.eta_ms);
+        assert_eq!(actual.download_speed, expected.download_speed);
+        assert_eq!(actual.disk_write_speed, expected.disk_write_speed);
+    }
+
+    #[test]
+    fn parses_status_report() {
+        let mut parser = ProgressParser::default();
+        let progress = parser.parse_output(STATUS_REPORT).unwrap();
+
+        assert_progress(&progress, &expected());
+    }
+
+    #[test]
+    fn parses_single_fields() {
+        assert_eq!(
+            eta_after("= Progress: 51.02% (9701/19015), Running for 00:14:52, ETA: 01:02:03"),
+            Some((3600 + 2 * 60 + 3) * 1000)
+        );
+        assert_eq!(
+            mib_after(" - Downloaded: 9.87 MiB, Written: 10.00 MiB", "Downloaded:"),
+            Some(mib_to_bytes(9.87))
+        );
+        assert_eq!(
+            rate_after(
+                " + Download\t- 1.97 MiB/s (raw) / 2.54 MiB/s (decompressed)",
+                "Download",
+                " MiB/s (raw)"
+            ),
+            Some(mib_to_bytes(1.97))
+        );
+        assert_eq!(
+            rate_after(
+                " + Disk\t- 2.54 MiB/s (write) / 0.00 MiB/s (read)",
+                "Disk",
+                " MiB/s (write)"
+            ),
+            Some(mib_to_bytes(2.54))
+        );
+    }
+
+    #[test]
+    fn ignores_unrelated_lines() {
+        let mut parser = ProgressParser::default();
+
+        assert!(parser
+            .parse_output("[DLManager] INFO:  - Cache usage: 15.00 MiB, active tasks: 32")
+            .is_none());
+        assert!(parser
+            .parse_output("[cli] INFO: Download size: unknown")
+            .is_none());
+        assert!(eta_after("ETA: soon").is_none());
+    }
+
+    #[test]
+    fn parses_chunks_split_mid_line() {
+        let mut framer = LineFramer::default();
+        let mut parser = ProgressParser::default();
+        let mut last = None;
+
+        for chunk in STATUS_REPORT.as_bytes().chunks(7) {
+            for line in framer.push(chunk) {
+                last = parser.parse_output(&line).or(last);
+            }
+        }
+
+        assert_progress(&last.unwrap(), &expected());
+    }
+
+    fn scheduled(id: &str, args: &[&str]) -> ScheduledCommand {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+        ScheduledCommand {
+            id: id.to_string(),
+            access: command_access(&args),
+            args,
+            slot: NEXT_SLOT.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    #[test]
+    fn reads_run_alongside_installs() {
+        let mut scheduler = ConfigScheduler::default();
+        scheduler
+            .running
+            .push(scheduled("install", &["install", "Fortnite", "-y"]));
+
+        assert!(scheduler.can_start_now(CommandAccess::ReadOnly));
+        assert!(scheduler.can_start_now(CommandAccess::Mutating));
+        assert!(!scheduler.can_start_now(CommandAccess::Installing));
+    }
+
+    #[test]
+    fn waiting_write_is_not_overtaken_by_reads() {
+        let mut scheduler = ConfigScheduler::default();
+        scheduler
+            .running
+            .push(scheduled("info", &["info", "Fortnite", "--json"]));
+
+        let (start, mut started) = oneshot::channel();
+        scheduler
+            .waiting
+            .push_back((scheduled("status", &["status", "--json"]), start));
+
+        assert!(!scheduler.can_start_now(CommandAccess::ReadOnly));
+
+        scheduler.running.clear();
+        scheduler.start_waiting();
+
+        assert!(started.try_recv().is_ok());
+        assert_eq!(scheduler.running[0].access, CommandAccess::Mutating);
+    }
+
+    #[test]
+    fn carries_total_over_when_resuming() {
+        // A resumed download only reports what is left
+        let mut parser = ProgressParser::with_total(mib_to_bytes(1000.0));
+        parser.parse_output("[cli] INFO: Download size: 400.00 MiB (Compression savings: 0.0%)");
+
+        let progress = parser
+            .parse_output("[DLManager] INFO:  - Downloaded: 100.00 MiB, Written: 100.00 MiB")
+            .unwrap();
+
+        assert_eq!(progress.downloaded, mib_to_bytes(700.0));
+        assert!((progress.percent - 70.0).abs() < 1e-9);
     }
 }
@@ -3,10 +3,26 @@ use tauri::generate_handler;
 use tauri::Manager;
 use tauri_plugin_prevent_default::{Builder as PreventDefaultBuilder, Flags, KeyboardShortcut};
 
-#[cfg(windows)]
+#[cfg(desktop)]
 mod app_monitor;
-#[cfg(windows)]
+#[cfg(desktop)]
+mod download_queue;
+#[cfg(desktop)]
+mod install;
+#[cfg(desktop)]
+mod install_mover;
+#[cfg(desktop)]
 mod legendary;
+#[cfg(desktop)]
+mod logs;
+#[cfg(desktop)]
+mod runner;
+#[cfg(desktop)]
+mod save_sync;
+#[cfg(desktop)]
+mod storage;
+#[cfg(desktop)]
+mod util;
 
 mod commands;
 mod types;
@@ -33,16 +49,17 @@ pub fn run() {
             }))
     }
 
-    #[cfg(windows)]
+    #[cfg(desktop)]
     {
         builder = builder
             .setup(|app| {
                 app_monitor::start_monitoring(app.handle().clone());
+                download_queue::start(app.handle().clone());
                 Ok(())
             })
             .on_window_event(|_window, event| {
                 if let tauri::WindowEvent::Destroyed = event {
-                    let _ = legendary::kill_legendary_processes();
+                    tauri::async_runtime::block_on(legendary::stop_all_streams());
                 }
             });
     }
@@ -55,13 +72,48 @@ pub fn run() {
     builder
         .invoke_handler(generate_handler![
             get_locale,
-            #[cfg(windows)] run_legendary,
-            #[cfg(windows)] start_legendary_stream,
-            #[cfg(windows)] stop_legendary_stream,
-            #[cfg(windows)] launch_app,
-            #[cfg(windows)] stop_app,
-            #[cfg(windows)] get_tracked_apps,
-            #[cfg(windows)] get_disk_space,
+            #[cfg(desktop)] run_legendary,
+            #[cfg(desktop)] cancel_legendary,
+            #[cfg(desktop)] legendary_list,
+            #[cfg(desktop)] legendary_status,
+            #[cfg(desktop)] legendary_info,
+            #[cfg(desktop)] legendary_installed,
+            #[cfg(desktop)] legendary_launch_data,
+            #[cfg(desktop)] start_legendary_stream,
+            #[cfg(desktop)] stop_legendary_stream,
+            #[cfg(desktop)] write_legendary_stream,
+            #[cfg(desktop)] get_legendary_stream_log,
+            #[cfg(desktop)] list_legendary_streams,
+            #[cfg(desktop)] list_legendary_logs,
+            #[cfg(desktop)] read_legendary_log,
+            #[cfg(desktop)] get_download_queue,
+            #[cfg(desktop)] enqueue_download,
+            #[cfg(desktop)] dequeue_download,
+            #[cfg(desktop)] move_download,
+            #[cfg(desktop)] pause_download,
+            #[cfg(desktop)] resume_download,
+            #[cfg(desktop)] clear_finished_downloads,
+            #[cfg(desktop)] get_disk_space_threshold,
+            #[cfg(desktop)] set_disk_space_threshold,
+            #[cfg(desktop)] launch_app,
+            #[cfg(desktop)] stop_app,
+            #[cfg(desktop)] get_tracked_apps,
+            #[cfg(desktop)] get_disk_space,
+            #[cfg(desktop)] list_volumes,
+            #[cfg(desktop)] preflight_install,
+            #[cfg(desktop)] list_dlc,
+            #[cfg(desktop)] list_install_tags,
+            #[cfg(desktop)] install_dlc,
+            #[cfg(desktop)] uninstall_dlc,
+            #[cfg(desktop)] import_installation,
+            #[cfg(desktop)] move_installation,
+            #[cfg(desktop)] cancel_move_installation,
+            #[cfg(desktop)] get_pending_moves,
+            #[cfg(desktop)] sync_saves,
+            #[cfg(desktop)] get_auto_save_sync,
+            #[cfg(desktop)] set_auto_save_sync,
+            #[cfg(desktop)] get_app_runner,
+            #[cfg(desktop)] set_app_runner,
         ])
         .plugin(prevent)
         .plugin(tauri_plugin_http::init())
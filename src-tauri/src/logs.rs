@@ -0,0 +1,254 @@
+use crate::storage;
+use crate::types::LegendaryLogInfo;
+use crate::util::now_ms;
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// A log is rotated once it grows past this size, keeping one older part next to it.
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+
+/// The oldest logs are deleted once there are more than this many.
+const MAX_LOG_FILES: usize = 50;
+
+/// Commands whose first argument after the command is the app id.
+const APP_COMMANDS: &[&str] = &[
+    "install",
+    "download",
+    "update",
+    "repair",
+    "uninstall",
+    "verify",
+    "verify-game",
+    "import",
+    "import-game",
+    "move",
+    "launch",
+    "info",
+    "list-files",
+    "sync-saves",
+    "download-saves",
+];
+
+/// Arguments whose value must not end up in a log file.
+const SECRET_ARGS: &[&str] = &["--token", "--code", "--sid"];
+
+/// Tees the output of a legendary invocation into `logs/legendary` in the data directory.
+/// Write errors are ignored, a broken log must not break the command itself.
+pub struct LogWriter {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl LogWriter {
+    pub fn create(app: &AppHandle, args: &[String]) -> Result<Self, String> {
+        let directory = logs_directory(app)?;
+        prune(&directory);
+
+        let (operation, app_id) = describe(args);
+        let file_name = match app_id {
+            Some(app_id) => format!("{}_{}_{}.log", now_ms(), operation, sanitize(app_id)),
+            None => format!("{}_{}.log", now_ms(), operation),
+        };
+
+        let path = directory.join(file_name);
+        let file = File::create(&path).map_err(|e| e.to_string())?;
+
+        let mut writer = Self {
+            path,
+            file: Some(file),
+            size: 0,
+        };
+        writer.write_line(&format!("$ legendary {}", redact(args).join(" ")));
+
+        Ok(writer)
+    }
+
+    pub fn write_line(&mut self, line: &str) {
+        self.write(format!("{}\n", line).as_bytes());
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        if self.size + bytes.len() as u64 > MAX_LOG_SIZE {
+            self.rotate();
+        }
+
+        if let Some(file) = &mut self.file {
+            if file.write_all(bytes).is_ok() {
+                self.size += bytes.len() as u64;
+            }
+        }
+    }
+
+    fn rotate(&mut self) {
+        // Closed first, Windows cannot rename a file that is still open
+        self.file = None;
+
+        let _ = fs::rename(&self.path, rotated_path(&self.path));
+
+        self.file = File::create(&self.path).ok();
+        self.size = 0;
+    }
+}
+
+/// Lists past logs, newest first, optionally only those of one app or operation.
+pub fn list_logs(
+    app: &AppHandle,
+    app_id: Option<&str>,
+    operation: Option<&str>,
+) -> Result<Vec<LegendaryLogInfo>, String> {
+    let directory = logs_directory(app)?;
+
+    let mut logs: Vec<LegendaryLogInfo> = log_files(&directory)
+        .into_iter()
+        .filter_map(|path| {
+            let file_name = path.file_name()?.to_string_lossy().to_string();
+            let mut info = parse_file_name(&file_name)?;
+
+            info.size = [path.clone(), rotated_path(&path)]
+                .iter()
+                .filter_map(|path| fs::metadata(path).ok())
+                .map(|metadata| metadata.len())
+                .sum();
+
+            Some(info)
+        })
+        .filter(|info| app_id.is_none_or(|app_id| info.app_id.as_deref() == Some(app_id)))
+        .filter(|info| operation.is_none_or(|operation| info.operation == operation))
+        .collect();
+
+    logs.sort_by_key(|log| Reverse(log.created_at));
+
+    Ok(logs)
+}
+
+/// Reads a log by the file name returned from [`list_logs`], including its rotated part.
+pub fn read_log(app: &AppHandle, file_name: &str) -> Result<String, String> {
+    // Also rejects anything that could point outside of the logs directory
+    if parse_file_name(file_name).is_none() {
+        return Err(format!("Invalid log file name: {}", file_name));
+    }
+
+    let path = logs_directory(app)?.join(file_name);
+
+    if !path.exists() {
+        return Err(format!("Log not found: {}", file_name));
+    }
+
+    let mut content = Vec::new();
+
+    for path in [rotated_path(&path), path] {
+        if let Ok(bytes) = fs::read(&path) {
+            content.extend(bytes);
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&content).to_string())
+}
+
+fn logs_directory(app: &AppHandle) -> Result<PathBuf, String> {
+    let directory = storage::data_directory(app)?.join("logs").join("legendary");
+    fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+
+    Ok(directory)
+}
+
+fn log_files(directory: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "log"))
+        .collect()
+}
+
+/// Makes room for a new log by deleting the oldest ones.
+fn prune(directory: &Path) {
+    let mut files = log_files(directory);
+
+    if files.len() < MAX_LOG_FILES {
+        return;
+    }
+
+    // File names start with the creation time, so they sort oldest first
+    files.sort();
+
+    for path in &files[..=files.len() - MAX_LOG_FILES] {
+        let _ = fs::remove_file(rotated_path(path));
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Parses `{created_at}_{operation}[_{app_id}].log`.
+fn parse_file_name(file_name: &str) -> Option<LegendaryLogInfo> {
+    if file_name.contains(['/', '\\']) {
+        return None;
+    }
+
+    let stem = file_name.strip_suffix(".log")?;
+    let mut parts = stem.splitn(3, '_');
+
+    let created_at = parts.next()?.parse().ok()?;
+    let operation = parts.next().filter(|operation| !operation.is_empty())?;
+    let app_id = parts.next().map(|app_id| app_id.to_string());
+
+    Some(LegendaryLogInfo {
+        file_name: file_name.to_string(),
+        operation: operation.to_string(),
+        app_id,
+        created_at,
+        size: 0,
+    })
+}
+
+fn describe(args: &[String]) -> (&str, Option<&str>) {
+    let mut positional = args.iter().filter(|arg| !arg.starts_with('-'));
+
+    let Some(operation) = positional.next() else {
+        return ("legendary", None);
+    };
+
+    let app_id = APP_COMMANDS
+        .contains(&operation.as_str())
+        .then(|| positional.next())
+        .flatten();
+
+    (operation, app_id.map(|app_id| app_id.as_str()))
+}
+
+fn redact(args: &[String]) -> Vec<String> {
+    let mut redacted = args.to_vec();
+
+    for index in 1..redacted.len() {
+        if SECRET_ARGS.contains(&args[index - 1].as_str()) {
+            redacted[index] = "<redacted>".to_string();
+        }
+    }
+
+    redacted
+}
+
+fn sanitize(app_id: &str) -> String {
+    app_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+
+    PathBuf::from(rotated)
+}
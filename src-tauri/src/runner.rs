@@ -0,0 +1,182 @@
+use crate::storage;
+use crate::types::{RunnerConfig, RunnerKind};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const RUNNERS_FILE: &str = "runners.json";
+
+pub struct RunnerInvocation {
+    pub program: String,
+    pub args: Vec<String>,
+    pub environment: HashMap<String, String>,
+}
+
+pub fn get_app_runner(app: &AppHandle, app_id: &str) -> Result<RunnerConfig, String> {
+    let runners: HashMap<String, RunnerConfig> = storage::read_json(app, RUNNERS_FILE)?;
+
+    Ok(runners.get(app_id).cloned().unwrap_or_default())
+}
+
+pub fn set_app_runner(app: &AppHandle, app_id: &str, runner: RunnerConfig) -> Result<(), String> {
+    validate_runner(&runner)?;
+
+    let mut runners: HashMap<String, RunnerConfig> = storage::read_json(app, RUNNERS_FILE)?;
+
+    if runner == RunnerConfig::default() {
+        runners.remove(app_id);
+    } else {
+        runners.insert(app_id.to_string(), runner);
+    }
+
+    storage::write_json(app, RUNNERS_FILE, &runners)
+}
+
+pub fn validate_runner(runner: &RunnerConfig) -> Result<(), String> {
+    parse_wrappers(&runner.wrappers)?;
+
+    if runner.kind == RunnerKind::Native {
+        return Ok(());
+    }
+
+    if cfg!(windows) {
+        return Err("Wine and Proton runners are not supported on Windows".to_string());
+    }
+
+    if runner.binary_path.is_empty() || !Path::new(&runner.binary_path).is_file() {
+        return Err(format!("Runner binary not found: {}", runner.binary_path));
+    }
+
+    if runner.prefix_path.is_empty() {
+        return Err("Runner prefix path is required".to_string());
+    }
+
+    Ok(())
+}
+
+/// Builds the final argv: legendary's `launch_command`, then the user's wrappers,
+/// then the runner and the game executable with its arguments.
+pub fn wrap_command(
+    runner: &RunnerConfig,
+    launch_command: &[String],
+    executable: &Path,
+    args: Vec<String>,
+) -> Result<RunnerInvocation, String> {
+    validate_runner(runner)?;
+    validate_launch_command(launch_command)?;
+
+    // Nothing may touch the disk before the whole command line was validated
+    let wrappers = [launch_command.to_vec(), parse_wrappers(&runner.wrappers)?].concat();
+
+    let executable = executable.to_string_lossy().to_string();
+    let mut environment = HashMap::new();
+
+    let (program, invocation_args) = match runner.kind {
+        RunnerKind::Native => (executable, args),
+        RunnerKind::Wine => {
+            environment.insert("WINEPREFIX".to_string(), runner.prefix_path.clone());
+
+            (
+                runner.binary_path.clone(),
+                [vec![executable], args].concat(),
+            )
+        }
+        RunnerKind::Proton => {
+            // Proton refuses to create the prefix if its parent directory is missing
+            fs::create_dir_all(&runner.prefix_path)
+                .map_err(|e| format!("Failed to create Proton prefix: {}", e))?;
+
+            environment.insert(
+                "STEAM_COMPAT_DATA_PATH".to_string(),
+                runner.prefix_path.clone(),
+            );
+            environment.insert(
+                "STEAM_COMPAT_CLIENT_INSTALL_PATH".to_string(),
+                default_steam_path(),
+            );
+
+            (
+                runner.binary_path.clone(),
+                [vec!["waitforexitandrun".to_string(), executable], args].concat(),
+            )
+        }
+    };
+
+    environment.extend(runner.environment.clone());
+
+    let Some((wrapper_program, wrapper_args)) = wrappers.split_first() else {
+        return Ok(RunnerInvocation {
+            program,
+            args: invocation_args,
+            environment,
+        });
+    };
+
+    Ok(RunnerInvocation {
+        program: wrapper_program.clone(),
+        args: [wrapper_args.to_vec(), vec![program], invocation_args].concat(),
+        environment,
+    })
+}
+
+/// Checks that the wrapper binary of legendary's `launch_command` exists. Its arguments
+/// are passed on as they are, e.g. a log file that the wrapper creates.
+fn validate_launch_command(launch_command: &[String]) -> Result<(), String> {
+    let Some(binary) = launch_command.first() else {
+        return Ok(());
+    };
+
+    if find_binary(binary).is_none() {
+        return Err(format!("Launch command binary not found: {}", binary));
+    }
+
+    Ok(())
+}
+
+/// Splits each wrapper into its arguments and checks that the wrapper binary exists.
+fn parse_wrappers(wrappers: &[String]) -> Result<Vec<String>, String> {
+    let mut parts = Vec::new();
+
+    for wrapper in wrappers {
+        let wrapper_parts =
+            shlex::split(wrapper).ok_or_else(|| format!("Invalid wrapper command: {}", wrapper))?;
+
+        let Some(binary) = wrapper_parts.first() else {
+            continue;
+        };
+
+        if find_binary(binary).is_none() {
+            return Err(format!("Wrapper binary not found: {}", binary));
+        }
+
+        parts.extend(wrapper_parts);
+    }
+
+    Ok(parts)
+}
+
+fn find_binary(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    let extensions: &[&str] = if cfg!(windows) { &["", ".exe"] } else { &[""] };
+
+    env::split_paths(&env::var_os("PATH")?)
+        .flat_map(|dir| {
+            extensions
+                .iter()
+                .map(move |extension| dir.join(format!("{}{}", name, extension)))
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+fn default_steam_path() -> String {
+    env::var("HOME")
+        .map(|home| format!("{}/.steam/steam", home))
+        .unwrap_or_default()
+}
@@ -0,0 +1,206 @@
+use crate::legendary;
+use crate::storage;
+use crate::types::{
+    CommandAccess, LegendaryError, LegendaryFailure, LegendaryOperation, RunOptions,
+    SaveFileOutcome, SaveSyncDirection, SaveSyncEvent, SaveSyncOutcome, SaveSyncResult,
+    SaveSyncTrigger,
+};
+use crate::util::now_ms;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+const AUTO_SYNC_FILE: &str = "save-sync.json";
+/// A launch waits for the sync, so a slow network call must not hold the game back for long.
+const LAUNCH_SYNC_TIMEOUT: Duration = Duration::from_secs(10);
+const EXIT_SYNC_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub async fn sync_saves(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    direction: SaveSyncDirection,
+    force: bool,
+) -> Result<SaveSyncResult, LegendaryError> {
+    let stream_id = format!("sync_saves_{}_{}", app_id, now_ms());
+
+    // Started by the user, who answers legendary's questions in the prompt dialog
+    run_sync(app, config_path, app_id, direction, force, true, &stream_id).await
+}
+
+async fn run_sync(
+    app: &AppHandle,
+    config_path: &str,
+    app_id: &str,
+    direction: SaveSyncDirection,
+    force: bool,
+    interactive: bool,
+    stream_id: &str,
+) -> Result<SaveSyncResult, LegendaryError> {
+    let operation = LegendaryOperation::SyncSaves {
+        app_id: app_id.to_string(),
+        direction,
+        force,
+        interactive,
+    };
+
+    let output = legendary::run_observed_stream(app, config_path, stream_id, &operation).await?;
+
+    if output.code != Some(0) {
+        return Err(LegendaryError::Failed {
+            code: output.code,
+            reason: output.error.unwrap_or(LegendaryFailure::Unknown),
+            stderr: output.stderr,
+        });
+    }
+
+    // Legendary asks for the save path on the first sync, it is only known afterwards
+    let save_path = legendary::get_app_info(app, config_path, app_id, &RunOptions::default())
+        .await?
+        .install
+        .and_then(|install| install.save_path);
+
+    Ok(parse_sync_output(
+        app_id,
+        &format!("{}\n{}", output.stderr, output.stdout),
+        save_path,
+    ))
+}
+
+pub fn get_auto_sync(app: &AppHandle, app_id: &str) -> Result<bool, String> {
+    let settings: HashMap<String, bool> = storage::read_json(app, AUTO_SYNC_FILE)?;
+
+    Ok(settings.get(app_id).copied().unwrap_or_default())
+}
+
+pub fn set_auto_sync(app: &AppHandle, app_id: &str, enabled: bool) -> Result<(), String> {
+    let mut settings: HashMap<String, bool> = storage::read_json(app, AUTO_SYNC_FILE)?;
+
+    if enabled {
+        settings.insert(app_id.to_string(), true);
+    } else {
+        settings.remove(app_id);
+    }
+
+    storage::write_json(app, AUTO_SYNC_FILE, &settings)
+}
+
+/// Pulls newer cloud saves before the game starts. It is skipped while other legendary
+/// commands are running, failures are reported through the `save_sync` event and never
+/// block the launch.
+pub async fn sync_before_launch(app: &AppHandle, app_id: &str) {
+    auto_sync(app, app_id, SaveSyncTrigger::BeforeLaunch).await;
+}
+
+/// Syncs the saves in the background once `app_monitor` sees the game exit.
+pub fn sync_after_exit(app: &AppHandle, app_id: &str) {
+    let app = app.clone();
+    let app_id = app_id.to_string();
+
+    tauri::async_runtime::spawn(async move {
+        auto_sync(&app, &app_id, SaveSyncTrigger::AfterExit).await;
+    });
+}
+
+async fn auto_sync(app: &AppHandle, app_id: &str, trigger: SaveSyncTrigger) {
+    if !get_auto_sync(app, app_id).unwrap_or_default() {
+        return;
+    }
+
+    let stream_id = format!("sync_saves_{}_{}", app_id, now_ms());
+    let sync = async {
+        let config_path = legendary::default_config_path(app)
+            .map_err(|message| LegendaryError::Process { message })?;
+
+        // The sync would wait in the scheduler, the saves are synced again after the game exits
+        if matches!(trigger, SaveSyncTrigger::BeforeLaunch)
+            && legendary::is_scheduler_busy(&config_path, CommandAccess::Mutating)
+        {
+            return Err(LegendaryError::InvalidOperation {
+                message: "Legendary is busy, the save sync was skipped".to_string(),
+            });
+        }
+
+        let info =
+            legendary::get_app_info(app, &config_path, app_id, &RunOptions::default()).await?;
+
+        if !info.game.cloud_saves_supported {
+            return Ok(None);
+        }
+
+        run_sync(
+            app,
+            &config_path,
+            app_id,
+            SaveSyncDirection::Auto,
+            false,
+            false,
+            &stream_id,
+        )
+        .await
+        .map(Some)
+    };
+
+    let timeout = match trigger {
+        SaveSyncTrigger::BeforeLaunch => LAUNCH_SYNC_TIMEOUT,
+        SaveSyncTrigger::AfterExit => EXIT_SYNC_TIMEOUT,
+    };
+
+    let (result, error) = match tokio::time::timeout(timeout, sync).await {
+        Ok(Ok(None)) => return,
+        Ok(Ok(result)) => (result, None),
+        Ok(Err(error)) => (None, Some(error.to_string())),
+        Err(_) => {
+            // Dropping the future leaves legendary running, it must not write saves the game
+            // has already loaded
+            let _ = legendary::stop_legendary_stream(&stream_id, false, None).await;
+            (None, Some("Save sync timed out".to_string()))
+        }
+    };
+
+    let event = SaveSyncEvent {
+        app_id: app_id.to_string(),
+        trigger,
+        result,
+        error,
+    };
+
+    let _ = app.emit("save_sync", &event);
+}
+
+/// Reads legendary's log lines, e.g. `[cli] INFO: Downloading remote savegame...`. Only
+/// downloads name their files, other outcomes are reported for the save as a whole.
+fn parse_sync_output(app_id: &str, output: &str, save_path: Option<String>) -> SaveSyncResult {
+    let mut outcome = SaveSyncOutcome::Skipped;
+    let mut files = Vec::new();
+
+    for line in output.lines() {
+        let message = line.split_once(": ").map_or(line, |(_, message)| message);
+        let lowercase = message.to_lowercase();
+
+        if lowercase.contains("downloading is disabled")
+            || lowercase.contains("uploading is disabled")
+        {
+            outcome = SaveSyncOutcome::Conflict;
+        } else if lowercase.starts_with("downloading remote savegame") {
+            outcome = SaveSyncOutcome::Downloaded;
+        } else if lowercase.starts_with("uploading local savegame") {
+            outcome = SaveSyncOutcome::Uploaded;
+        } else if let Some(file) = message
+            .strip_prefix("Downloading \"")
+            .and_then(|rest| rest.split('"').next())
+        {
+            files.push(SaveFileOutcome {
+                path: file.to_string(),
+                outcome: SaveSyncOutcome::Downloaded,
+            });
+        }
+    }
+
+    SaveSyncResult {
+        app_id: app_id.to_string(),
+        outcome,
+        save_path,
+        files,
+    }
+}
@@ -0,0 +1,43 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+/// Same directory the frontend uses for its settings files.
+pub fn data_directory(app: &AppHandle) -> Result<PathBuf, String> {
+    let directory = app
+        .path()
+        .data_dir()
+        .map_err(|e| e.to_string())?
+        .join("spitfire-launcher");
+
+    fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+
+    Ok(directory)
+}
+
+pub fn read_json<T: DeserializeOwned + Default>(
+    app: &AppHandle,
+    file_name: &str,
+) -> Result<T, String> {
+    let path = data_directory(app)?.join(file_name);
+
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", file_name, e))
+}
+
+pub fn write_json<T: Serialize>(app: &AppHandle, file_name: &str, value: &T) -> Result<(), String> {
+    let path = data_directory(app)?.join(file_name);
+    let temp_path = path.with_extension("json.tmp");
+
+    let content = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+
+    fs::write(&temp_path, content).map_err(|e| e.to_string())?;
+    fs::rename(&temp_path, &path).map_err(|e| e.to_string())
+}
@@ -7,6 +7,21 @@ pub struct CommandOutput {
     pub signal: Option<i32>,
     pub stdout: String,
     pub stderr: String,
+    /// Set when the command failed or reported a known problem, see `classify_failure`.
+    pub error: Option<LegendaryFailure>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LegendaryFailure {
+    NotLoggedIn,
+    TokenExpired,
+    InsufficientDiskSpace,
+    Network,
+    NotOwned,
+    InstallPathConflict,
+    NeedsRepair,
+    Unknown,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -14,6 +29,9 @@ pub struct CommandOutput {
 pub enum EventType {
     Stdout,
     Stderr,
+    Progress,
+    /// Legendary is waiting for an answer on stdin, `data` holds the question.
+    Prompt,
     Terminated,
     Error,
 }
@@ -21,19 +39,110 @@ pub enum EventType {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StreamEvent {
     pub stream_id: String,
+    /// Increases by one for every event of the stream, starting at 1.
+    pub seq: u64,
     pub event_type: EventType,
     pub data: String,
     pub code: Option<i32>,
     pub signal: Option<i32>,
+    pub progress: Option<DownloadProgress>,
+    pub termination: Option<TerminationReason>,
+    /// Set on `terminated`, classified from the stderr of the stream.
+    pub reason: Option<LegendaryFailure>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamState {
+    /// Waiting for the scheduler to let it start.
+    Queued,
+    Running,
+    Finished,
+}
+
+#[derive(Debug, Serialize, Clone)]
 pub struct StreamInfo {
     pub stream_id: String,
+    pub args: Vec<String>,
+    pub state: StreamState,
+    pub pid: Option<u32>,
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    /// Raw stdout and stderr bytes received so far.
+    pub output_bytes: u64,
+    pub last_progress: Option<DownloadProgress>,
+    /// Sequence number of the latest event, to resume from with `get_legendary_stream_log`.
+    pub last_seq: u64,
+}
+
+/// How a stream ended, only set on `Terminated` events.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminationReason {
+    /// The process exited on its own.
+    Exited,
+    /// The process stopped after being interrupted.
+    Interrupted,
+    /// The process was force killed, either directly or after the grace period ran out.
+    Killed,
+    /// The stream was stopped while waiting for its turn, legendary never started.
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandAccess {
+    /// Only reads legendary's data.
+    ReadOnly,
+    /// Short commands that write the config, e.g. `status` refreshing the session.
+    Mutating,
+    /// Commands that hold legendary's installed data lock for as long as they run.
+    Installing,
+}
+
+impl CommandAccess {
+    /// Installs only conflict with each other, so reads and short config writes can still
+    /// run while a download takes hours.
+    pub fn conflicts_with(self, other: CommandAccess) -> bool {
+        match self {
+            CommandAccess::ReadOnly => other == CommandAccess::Mutating,
+            CommandAccess::Mutating => other != CommandAccess::Installing,
+            CommandAccess::Installing => other == CommandAccess::Installing,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ScheduledCommand {
+    /// The stream id, or the request id of a `run_legendary` call.
+    pub id: String,
+    pub args: Vec<String>,
+    pub access: CommandAccess,
+    /// Unique per command, a stream and a request may share an id.
+    #[serde(skip)]
+    pub slot: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SchedulerEvent {
+    pub config_path: String,
+    pub running: Vec<ScheduledCommand>,
+    pub waiting: Vec<ScheduledCommand>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    pub total: u64,
+    pub percent: f64,
+    pub eta_ms: u64,
+    pub download_speed: u64,
+    pub disk_write_speed: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LaunchData {
+    #[serde(default)]
     pub game_id: String,
     pub game_parameters: Vec<String>,
     pub game_executable: String,
@@ -45,6 +154,131 @@ pub struct LaunchData {
     pub environment: HashMap<String, String>,
     pub pre_launch_command: String,
     pub pre_launch_wait: bool,
+    #[serde(default)]
+    pub runner: Option<RunnerConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunnerKind {
+    #[default]
+    Native,
+    Wine,
+    Proton,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RunnerConfig {
+    pub kind: RunnerKind,
+    #[serde(default)]
+    pub binary_path: String,
+    #[serde(default)]
+    pub prefix_path: String,
+    #[serde(default)]
+    pub environment: HashMap<String, String>,
+    #[serde(default)]
+    pub wrappers: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueItemStatus {
+    Queued,
+    Downloading,
+    Completed,
+    Failed,
+    Paused,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadOperation {
+    Install,
+    Update,
+    Repair,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueDirection {
+    Up,
+    Down,
+}
+
+/// The app as the frontend's library knows it, kept as-is so the queue can be rendered.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedApp {
+    pub id: String,
+    pub title: String,
+    pub images: QueuedAppImages,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_repair: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_update: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_size: Option<u64>,
+    pub install_size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installed: Option<bool>,
+    pub can_run_offline: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueuedAppImages {
+    pub tall: String,
+    pub wide: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DownloadOptions {
+    pub base_path: String,
+    #[serde(default)]
+    pub no_https: bool,
+    /// Filled from the saved selection of the app when not given.
+    #[serde(default)]
+    pub install_tags: Option<Vec<String>>,
+    /// Installed after the base game, only used by installs.
+    #[serde(default)]
+    pub dlc_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueueItem {
+    pub status: QueueItemStatus,
+    pub item: QueuedApp,
+    pub operation: DownloadOperation,
+    pub options: DownloadOptions,
+    pub added_at: u64,
+    pub started_at: Option<u64>,
+    pub completed_at: Option<u64>,
+    pub total_download_size: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DownloadQueueState {
+    pub items: Vec<QueueItem>,
+    pub downloading_app_id: Option<String>,
+    pub progress: Option<DownloadProgress>,
+    /// The paused download was paused by the disk space watcher.
+    pub low_disk_space: bool,
+}
+
+/// Payload of the `low_disk_space` and `disk_space_recovered` events.
+#[derive(Debug, Serialize, Clone)]
+pub struct DiskSpaceEvent {
+    pub app_id: String,
+    pub path: String,
+    pub available: u64,
+    pub threshold: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DownloadFinishedEvent {
+    pub item: QueuedApp,
+    pub operation: DownloadOperation,
+    pub success: bool,
+    pub reason: Option<LegendaryFailure>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -61,7 +295,7 @@ pub struct AppStateEvent {
     pub state: AppState,
 }
 
-#[cfg(windows)]
+#[cfg(desktop)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct TrackedApp {
@@ -76,3 +310,553 @@ pub struct DiskSpace {
     pub total: u64,
     pub available: u64,
 }
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportResult {
+    pub app_id: String,
+    pub install_path: String,
+    pub version: String,
+    pub install_size: u64,
+    pub needs_repair: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveStage {
+    Copying,
+    Registering,
+    Verifying,
+    RemovingSource,
+    Completed,
+    RollingBack,
+    RolledBack,
+    Failed,
+}
+
+/// Payload of the `move_progress` event, `copied` and `total` are bytes.
+#[derive(Debug, Serialize, Clone)]
+pub struct MoveProgress {
+    pub app_id: String,
+    pub stage: MoveStage,
+    pub copied: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MoveResult {
+    pub app_id: String,
+    pub install_path: String,
+    /// The old files could not be removed and have to be deleted by hand.
+    pub source_removed: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Volume {
+    pub mount_point: String,
+    /// Volume label on Windows, device name elsewhere.
+    pub label: String,
+    pub file_system: String,
+    pub total: u64,
+    pub available: u64,
+    pub removable: bool,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PreflightReport {
+    pub app_id: String,
+    /// `None` when legendary has no cached metadata to take the folder name from.
+    pub install_path: Option<String>,
+    pub download_size: u64,
+    pub disk_size: u64,
+    /// Sizes of the DLC selected for the install, already part of the space check.
+    pub dlc_download_size: u64,
+    pub dlc_disk_size: u64,
+    /// Every DLC the account owns for the app.
+    pub dlcs: Vec<DlcInfo>,
+    pub available_space: Option<u64>,
+    pub can_install: bool,
+    pub problems: Vec<PreflightProblem>,
+    /// Shown to the user without blocking the install, e.g. the leftovers of an interrupted
+    /// install that legendary can resume.
+    pub warnings: Vec<PreflightProblem>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PreflightProblem {
+    AlreadyInstalled {
+        install_path: String,
+    },
+    ManifestUnavailable,
+    InvalidPath,
+    InsufficientSpace {
+        required: u64,
+        available: u64,
+    },
+    /// Less than the low disk space threshold would be left after the install.
+    BelowDiskSpaceThreshold {
+        left: u64,
+        threshold: u64,
+    },
+    NotWritable {
+        message: String,
+    },
+    DiskSpaceUnavailable {
+        message: String,
+    },
+    TargetNotEmpty {
+        path: String,
+    },
+    DlcNotOwned {
+        app_id: String,
+    },
+    /// The DLC's size is not part of the space check.
+    DlcSizeUnknown {
+        app_id: String,
+    },
+}
+
+/// A selective download tag of an app, e.g. a language pack or an optional component.
+#[derive(Debug, Serialize, Clone)]
+pub struct InstallTag {
+    pub tag: String,
+    pub download_size: u64,
+    pub disk_size: u64,
+    pub file_count: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct InstallTagInfo {
+    pub app_id: String,
+    pub tags: Vec<InstallTag>,
+    /// The saved selection, or the tags of the current install when nothing was saved.
+    pub selected: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DlcInfo {
+    pub app_id: String,
+    pub title: String,
+    pub installed: bool,
+    /// `None` when legendary could not read the DLC's manifest.
+    pub download_size: Option<u64>,
+    pub disk_size: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DlcResult {
+    pub app_id: String,
+    pub success: bool,
+    pub reason: Option<LegendaryFailure>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LegendaryLogInfo {
+    pub file_name: String,
+    pub operation: String,
+    pub app_id: Option<String>,
+    pub created_at: u64,
+    /// Size in bytes, including the rotated part.
+    pub size: u64,
+}
+
+/// Everything the launcher asks legendary to do. The sidecar arguments are only ever
+/// built from this, so the webview cannot pass arbitrary flags.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum LegendaryOperation {
+    Install {
+        app_id: String,
+        #[serde(default)]
+        options: InstallOptions,
+    },
+    Update {
+        app_id: String,
+        #[serde(default)]
+        options: InstallOptions,
+    },
+    Repair {
+        app_id: String,
+        #[serde(default)]
+        options: InstallOptions,
+    },
+    Verify {
+        app_id: String,
+    },
+    Uninstall {
+        app_id: String,
+        #[serde(default)]
+        keep_files: bool,
+    },
+    Import {
+        app_id: String,
+        path: String,
+    },
+    Move {
+        app_id: String,
+        new_base_path: String,
+        /// Only update legendary's install record, the files were moved already.
+        #[serde(default)]
+        skip_move: bool,
+    },
+    /// Resolves the launch command without starting anything, `launch_app` starts the game.
+    Launch {
+        app_id: String,
+    },
+    Auth {
+        action: AuthAction,
+    },
+    EglSync,
+    SyncSaves {
+        app_id: String,
+        #[serde(default)]
+        direction: SaveSyncDirection,
+        /// Overwrite the other side even when it is newer.
+        #[serde(default)]
+        force: bool,
+        /// Let legendary ask before syncing, the answers are sent with `write_stream`.
+        #[serde(default)]
+        interactive: bool,
+    },
+    Info {
+        app_id: String,
+    },
+    ListFiles {
+        app_id: String,
+    },
+    List,
+    ListInstalled,
+    Status,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveSyncDirection {
+    Upload,
+    Download,
+    /// Let legendary pick the newer side.
+    #[default]
+    Auto,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveSyncOutcome {
+    Uploaded,
+    Downloaded,
+    Skipped,
+    /// The requested direction would have overwritten newer saves, nothing was synced.
+    Conflict,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SaveFileOutcome {
+    pub path: String,
+    pub outcome: SaveSyncOutcome,
+}
+
+/// Legendary only names the files it downloads, so `files` is empty for uploads, skips and
+/// conflicts and `outcome` covers the whole save.
+#[derive(Debug, Serialize, Clone)]
+pub struct SaveSyncResult {
+    pub app_id: String,
+    pub outcome: SaveSyncOutcome,
+    pub save_path: Option<String>,
+    /// Only the downloaded files.
+    pub files: Vec<SaveFileOutcome>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveSyncTrigger {
+    BeforeLaunch,
+    AfterExit,
+}
+
+/// Payload of the `save_sync` event sent for automatic syncs.
+#[derive(Debug, Serialize, Clone)]
+pub struct SaveSyncEvent {
+    pub app_id: String,
+    pub trigger: SaveSyncTrigger,
+    pub result: Option<SaveSyncResult>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthAction {
+    Login { exchange_code: String },
+    Logout,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct InstallOptions {
+    pub base_path: Option<String>,
+    pub no_https: bool,
+    pub skip_sdl: bool,
+    pub skip_dlcs: bool,
+    /// Selective download tags to install, takes precedence over `skip_sdl`. An empty
+    /// set installs the base game only.
+    pub install_tags: Option<Vec<String>>,
+}
+
+/// Per-call options for `run_legendary`. Giving a `request_id` lets the call be cancelled
+/// with `cancel_legendary`.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RunOptions {
+    pub timeout_ms: Option<u64>,
+    pub request_id: Option<String>,
+    /// Tee the output into a log file, see `list_legendary_logs`.
+    pub log: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LegendaryError {
+    Process {
+        message: String,
+    },
+    Failed {
+        code: Option<i32>,
+        stderr: String,
+        reason: LegendaryFailure,
+    },
+    InvalidOutput {
+        command: String,
+        message: String,
+    },
+    TimedOut {
+        timeout_ms: u64,
+    },
+    Cancelled,
+    InvalidOperation {
+        message: String,
+    },
+}
+
+impl std::fmt::Display for LegendaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LegendaryError::Process { message } => write!(f, "{}", message),
+            LegendaryError::Failed { code, stderr, .. } => {
+                write!(f, "Legendary exited with code {:?}: {}", code, stderr)
+            }
+            LegendaryError::InvalidOutput { command, message } => {
+                write!(
+                    f,
+                    "Unexpected output from `legendary {}`: {}",
+                    command, message
+                )
+            }
+            LegendaryError::TimedOut { timeout_ms } => {
+                write!(f, "Legendary did not finish within {} ms", timeout_ms)
+            }
+            LegendaryError::Cancelled => write!(f, "Legendary command was cancelled"),
+            LegendaryError::InvalidOperation { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryGame {
+    pub app_name: String,
+    pub app_title: String,
+    pub metadata: LegendaryGameMetadata,
+    #[serde(default)]
+    pub asset_infos: HashMap<String, LegendaryAssetInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LegendaryGameMetadata {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub developer: String,
+    #[serde(default)]
+    pub entitlement_name: String,
+    #[serde(default)]
+    pub entitlement_type: String,
+    #[serde(default)]
+    pub key_images: Vec<LegendaryKeyImage>,
+    #[serde(default)]
+    pub categories: Vec<LegendaryCategory>,
+    #[serde(default)]
+    pub custom_attributes: HashMap<String, LegendaryCustomAttribute>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LegendaryKeyImage {
+    #[serde(rename = "type")]
+    pub image_type: String,
+    pub url: String,
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryCategory {
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryCustomAttribute {
+    #[serde(rename = "type")]
+    pub attribute_type: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryAssetInfo {
+    pub app_name: String,
+    pub asset_id: String,
+    pub build_version: String,
+    pub catalog_item_id: String,
+    pub label_name: String,
+    pub namespace: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryStatus {
+    pub account: Option<String>,
+    pub games_available: u32,
+    pub games_installed: u32,
+    pub egl_sync_enabled: bool,
+    pub config_directory: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryAppInfo {
+    pub game: LegendaryAppInfoGame,
+    pub install: Option<LegendaryAppInfoInstall>,
+    #[serde(default)]
+    pub manifest: Option<LegendaryAppInfoManifest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryAppInfoGame {
+    pub app_name: String,
+    pub title: String,
+    pub version: String,
+    #[serde(default)]
+    pub platform_versions: HashMap<String, String>,
+    #[serde(default)]
+    pub cloud_saves_supported: bool,
+    pub cloud_save_folder: Option<String>,
+    pub cloud_save_folder_mac: Option<String>,
+    #[serde(default)]
+    pub is_dlc: bool,
+    pub external_activation: Option<String>,
+    #[serde(default)]
+    pub owned_dlc: Vec<LegendaryDlcRef>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryAppInfoInstall {
+    pub platform: String,
+    pub version: String,
+    pub disk_size: u64,
+    pub install_path: String,
+    pub save_path: Option<String>,
+    pub synced_egl_guid: Option<String>,
+    #[serde(default)]
+    pub install_tags: Vec<String>,
+    #[serde(default)]
+    pub installed_dlc: Vec<LegendaryDlcRef>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryAppInfoManifest {
+    pub size: u64,
+    #[serde(rename = "type")]
+    pub manifest_type: String,
+    pub version: u32,
+    pub feature_level: u32,
+    pub app_name: String,
+    pub launch_exe: String,
+    #[serde(default)]
+    pub launch_command: String,
+    pub build_version: String,
+    pub build_id: String,
+    #[serde(default)]
+    pub install_tags: Vec<String>,
+    pub num_files: u64,
+    pub num_chunks: u64,
+    pub disk_size: u64,
+    pub download_size: u64,
+    #[serde(default)]
+    pub tag_disk_size: Vec<LegendaryTagSize>,
+    #[serde(default)]
+    pub tag_download_size: Vec<LegendaryTagSize>,
+}
+
+/// A file of the manifest, from `legendary list-files --json`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LegendaryManifestFile {
+    pub filename: String,
+    pub file_size: u64,
+    #[serde(default)]
+    pub install_tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryTagSize {
+    pub tag: String,
+    pub size: u64,
+    pub count: u64,
+}
+
+/// Legendary has reported DLC both as plain app names and as objects over time.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum LegendaryDlcRef {
+    Name(String),
+    Detailed {
+        app_name: String,
+        #[serde(default)]
+        title: String,
+    },
+}
+
+impl LegendaryDlcRef {
+    pub fn app_name(&self) -> &str {
+        match self {
+            LegendaryDlcRef::Name(app_name) => app_name,
+            LegendaryDlcRef::Detailed { app_name, .. } => app_name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegendaryInstalledApp {
+    pub app_name: String,
+    pub title: String,
+    pub version: String,
+    pub install_path: String,
+    pub install_size: u64,
+    pub executable: String,
+    pub platform: String,
+    #[serde(default)]
+    pub base_urls: Vec<String>,
+    #[serde(default)]
+    pub can_run_offline: bool,
+    pub egl_guid: Option<String>,
+    #[serde(default)]
+    pub install_tags: Vec<String>,
+    #[serde(default)]
+    pub is_dlc: bool,
+    #[serde(default)]
+    pub launch_parameters: String,
+    #[serde(default)]
+    pub manifest_path: Option<String>,
+    #[serde(default)]
+    pub needs_verification: bool,
+    pub save_path: Option<String>,
+}
@@ -103,7 +103,7 @@ export const SidebarCategories = derived(t, ($t) => [
       }
     ]
   },
-  platform === 'windows' && {
+  (platform === 'windows' || platform === 'linux') && {
     key: 'downloader',
     name: $t('sidebar.categories.downloader'),
     items: [
@@ -170,8 +170,7 @@ const downloaderStorage = new DataStorage<DownloaderSettings>(
     sendNotifications: true,
     favoriteApps: [],
     hiddenApps: [],
-    perAppAutoUpdate: {},
-    queue: {}
+    perAppAutoUpdate: {}
   },
   downloaderSettingsSchema
 );
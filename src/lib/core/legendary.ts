@@ -6,7 +6,7 @@ import { ownedApps } from '$lib/stores';
 import { t } from '$lib/utils/util';
 import type { AccountData } from '$types/accounts';
 import type { EpicOAuthData } from '$types/game/authorizations';
-import type { LegendaryAppInfo, LegendaryInstalledList, LegendaryLaunchData, LegendaryList, LegendaryStatus } from '$types/legendary';
+import type { DlcInfo, DlcResult, ImportResult, InstallTagInfo, LegendaryAppInfo, LegendaryCommandError, LegendaryFailure, LegendaryInstalledList, LegendaryLaunchData, LegendaryList, LegendaryOperation, MoveResult, LegendaryRunOptions, LegendaryStatus, PreflightReport, RunnerConfig, SaveSyncDirection, SaveSyncResult } from '$types/legendary';
 import { path } from '@tauri-apps/api';
 import { invoke } from '@tauri-apps/api/core';
 import { readTextFile } from '@tauri-apps/plugin-fs';
@@ -19,14 +19,64 @@ type ExecuteResult<T = any> = {
   signal: number | null;
   stdout: T;
   stderr: string;
+  error: LegendaryFailure | null;
+};
+
+// Status refreshes the session online, a stuck network call must not block the library
+const STATUS_TIMEOUT_MS = 30_000;
+
+export type StreamProgress = {
+  downloaded: number;
+  total: number;
+  percent: number;
+  eta_ms: number;
+  download_speed: number;
+  disk_write_speed: number;
 };
 
 export type StreamEvent = {
   stream_id: string;
-  event_type: 'stdout' | 'stderr' | 'terminated' | 'error';
+  seq: number;
+  event_type: 'stdout' | 'stderr' | 'progress' | 'prompt' | 'terminated' | 'error';
   data: string;
   code?: number;
   signal?: number;
+  progress?: StreamProgress;
+  termination?: 'exited' | 'interrupted' | 'killed' | 'cancelled';
+  reason?: LegendaryFailure | null;
+};
+
+export type StreamInfo = {
+  stream_id: string;
+  args: string[];
+  state: 'queued' | 'running' | 'finished';
+  pid: number | null;
+  created_at: number;
+  started_at: number | null;
+  output_bytes: number;
+  last_progress: StreamProgress | null;
+  last_seq: number;
+};
+
+export type LegendaryLogInfo = {
+  file_name: string;
+  operation: string;
+  app_id: string | null;
+  created_at: number;
+  size: number;
+};
+
+export type ScheduledCommand = {
+  id: string;
+  args: string[];
+  access: 'read_only' | 'mutating' | 'installing';
+};
+
+// Payload of `legendary_scheduler_changed`, a command's index in `waiting` is its queue position
+export type SchedulerEvent = {
+  config_path: string;
+  running: ScheduledCommand[];
+  waiting: ScheduledCommand[];
 };
 
 export default class Legendary {
@@ -36,24 +86,106 @@ export default class Legendary {
     account?: string;
   } = {};
 
-  static async execute<T>(args: string[]): Promise<ExecuteResult<T>> {
+  static async execute<T>(operation: LegendaryOperation, options?: LegendaryRunOptions): Promise<ExecuteResult<T>> {
     try {
       const configPath = await Legendary.getConfigPath();
-      const result = await invoke<ExecuteResult>('run_legendary', { configPath, args });
-
-      if (args.includes('--json')) {
-        result.stdout = JSON.parse(result.stdout) as T;
-      }
+      const result = await invoke<ExecuteResult>('run_legendary', { configPath, operation, options });
 
       if (result.code !== 0) {
-        throw new Error(result.stderr);
+        throw {
+          kind: 'failed',
+          code: result.code,
+          stderr: result.stderr,
+          reason: result.error || 'unknown'
+        } satisfies LegendaryCommandError;
       }
 
       return result;
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
-      throw new LegendaryError(message);
+      throw new LegendaryError(Legendary.formatCommandError(error), Legendary.getFailureReason(error));
+    }
+  }
+
+  static cancel(requestId: string) {
+    return invoke<boolean>('cancel_legendary', { requestId });
+  }
+
+  // Answers a 'prompt' event, the answer needs its own trailing newline
+  static writeStream(streamId: string, data: string) {
+    return invoke<void>('write_legendary_stream', { streamId, data });
+  }
+
+  static stopStream(streamId: string) {
+    return invoke<boolean>('stop_legendary_stream', { streamId, forceKillAll: false });
+  }
+
+  static listLogs(filter: { appId?: string; operation?: string } = {}) {
+    return invoke<LegendaryLogInfo[]>('list_legendary_logs', filter);
+  }
+
+  static readLog(fileName: string) {
+    return invoke<string>('read_legendary_log', { fileName });
+  }
+
+  static listStreams() {
+    return invoke<StreamInfo[]>('list_legendary_streams');
+  }
+
+  // Replays the events of a stream, used to reattach to one that started before the listener
+  static getStreamLog(streamId: string, sinceSeq?: number) {
+    return invoke<StreamEvent[]>('get_legendary_stream_log', { streamId, sinceSeq });
+  }
+
+  static async invokeTyped<T>(command: string, args: Record<string, unknown> = {}): Promise<T> {
+    try {
+      const configPath = await Legendary.getConfigPath();
+      return await invoke<T>(command, { configPath, ...args });
+    } catch (error) {
+      throw new LegendaryError(Legendary.formatCommandError(error), Legendary.getFailureReason(error));
+    }
+  }
+
+  static formatCommandError(error: unknown) {
+    if (typeof error !== 'object' || !error || !('kind' in error)) {
+      return error instanceof Error ? error.message : String(error);
     }
+
+    const commandError = error as LegendaryCommandError;
+    switch (commandError.kind) {
+      case 'process':
+        return commandError.message;
+      case 'failed':
+        return Legendary.describeFailure(commandError.reason) || commandError.stderr;
+      case 'invalid_output':
+        return `Unexpected output from legendary ${commandError.command}: ${commandError.message}`;
+      case 'timed_out':
+        return `Legendary did not finish within ${commandError.timeout_ms / 1000} seconds`;
+      case 'cancelled':
+        return 'Legendary command was cancelled';
+      case 'invalid_operation':
+        return commandError.message;
+    }
+  }
+
+  static describeFailure(reason: LegendaryFailure) {
+    const translate = get(t);
+    const messages: Record<LegendaryFailure, string | null> = {
+      not_logged_in: translate('library.legendaryErrors.notLoggedIn'),
+      token_expired: translate('library.legendaryErrors.tokenExpired'),
+      insufficient_disk_space: translate('library.legendaryErrors.insufficientDiskSpace'),
+      network: translate('library.legendaryErrors.network'),
+      not_owned: translate('library.legendaryErrors.notOwned'),
+      install_path_conflict: translate('library.legendaryErrors.installPathConflict'),
+      needs_repair: translate('library.legendaryErrors.needsRepair'),
+      unknown: null
+    };
+
+    return messages[reason];
+  }
+
+  private static getFailureReason(error: unknown) {
+    const commandError = error as LegendaryCommandError | undefined;
+    return commandError?.kind === 'failed' ? commandError.reason : undefined;
   }
 
   static async getConfigPath() {
@@ -65,18 +197,19 @@ export default class Legendary {
     const accessToken = await Authentication.verifyOrRefreshAccessToken(account);
     const { code: exchange } = await Authentication.getExchangeCodeUsingAccessToken(accessToken);
 
-    const data = await Legendary.execute<string>(['auth', '--token', exchange]);
+    const data = await Legendary.execute<string>({ operation: 'auth', action: { login: { exchange_code: exchange } } });
     Legendary.caches.account = account.accountId;
     if (Legendary.caches.status) Legendary.caches.status.account = account.accountId;
 
     Legendary.cachedApps = false;
     await Legendary.cacheApps();
+    await DownloadManager.migrateLegacyQueue().catch(console.error);
 
     return data;
   }
 
   static async logout() {
-    const data = await Legendary.execute<string>(['auth', '--delete']);
+    const data = await Legendary.execute<string>({ operation: 'auth', action: 'logout' });
 
     Legendary.cachedApps = false;
     Legendary.caches.account = undefined;
@@ -86,20 +219,18 @@ export default class Legendary {
   }
 
   static getList() {
-    return Legendary.execute<LegendaryList>(['list', '--json']);
+    return Legendary.invokeTyped<LegendaryList>('legendary_list');
   }
 
   static async getStatus() {
     if (Legendary.caches.status) return Legendary.caches.status;
 
-    const { stdout } = await Legendary.execute<LegendaryStatus>(['status', '--json']);
-
-    if (stdout.account === '<not logged in>') {
-      stdout.account = null;
-    }
+    const status = await Legendary.invokeTyped<LegendaryStatus>('legendary_status', {
+      options: { timeout_ms: STATUS_TIMEOUT_MS }
+    });
 
-    Legendary.caches.status = stdout;
-    return stdout;
+    Legendary.caches.status = status;
+    return status;
   }
 
   static async getAccount() {
@@ -123,30 +254,46 @@ export default class Legendary {
   }
 
   static getAppInfo(appId: string) {
-    return Legendary.execute<LegendaryAppInfo>(['info', appId, '--json']);
+    return Legendary.invokeTyped<LegendaryAppInfo>('legendary_info', { appId });
+  }
+
+  static preflightInstall(appId: string, basePath: string, dlcIds: string[] = []) {
+    return Legendary.invokeTyped<PreflightReport>('preflight_install', { appId, basePath, dlcIds });
+  }
+
+  static listInstallTags(appId: string) {
+    return Legendary.invokeTyped<InstallTagInfo>('list_install_tags', { appId });
+  }
+
+  static listDlc(appId: string) {
+    return Legendary.invokeTyped<DlcInfo[]>('list_dlc', { appId });
+  }
+
+  // Each DLC is installed on its own stream, named `${streamId}_${dlcId}`
+  static installDlc(appId: string, dlcIds: string[], streamId = `dlc_${appId}_${Date.now()}`) {
+    return Legendary.invokeTyped<DlcResult[]>('install_dlc', { appId, dlcIds, streamId });
+  }
+
+  static uninstallDlc(appId: string, dlcIds: string[]) {
+    return Legendary.invokeTyped<DlcResult[]>('uninstall_dlc', { appId, dlcIds });
   }
 
   static getInstalledList() {
-    return Legendary.execute<LegendaryInstalledList>(['list-installed', '--json']);
+    return Legendary.invokeTyped<LegendaryInstalledList>('legendary_installed');
   }
 
   static async syncEGL() {
-    return Legendary.execute(['egl-sync', '-y', '--enable-sync']);
+    return Legendary.execute({ operation: 'egl_sync' });
   }
 
   static async launch(appId: string) {
-    const { stdout: launchData } = await Legendary.execute<LegendaryLaunchData>(['launch', appId, '--dry-run', '--json']);
-    return invoke<number>('launch_app', {
-      launchData: {
-        ...launchData,
-        game_id: appId
-      }
-    });
+    const launchData = await Legendary.invokeTyped<LegendaryLaunchData>('legendary_launch_data', { appId });
+    return invoke<number>('launch_app', { launchData });
   }
 
   static async verify(appId: string) {
-    const { stderr } = await Legendary.execute<string>(['verify', appId, '-y', '--skip-sdl']);
-    const requiresRepair = stderr.includes('repair your game installation');
+    const { error } = await Legendary.execute<string>({ operation: 'verify', app_id: appId });
+    const requiresRepair = error === 'needs_repair';
     const requiredRepair = get(ownedApps).find(app => app.id === appId)?.requiresRepair || false;
 
     if (requiresRepair !== requiredRepair) {
@@ -162,8 +309,57 @@ export default class Legendary {
     return { requiresRepair };
   }
 
+  // Progress is emitted on the streams `streamId` and `${streamId}_verify`
+  static async importInstallation(appId: string, path: string, streamId = `import_${appId}_${Date.now()}`) {
+    const result = await Legendary.invokeTyped<ImportResult>('import_installation', { appId, path, streamId });
+
+    ownedApps.update(current => {
+      return current.map(app =>
+        app.id === appId
+          ? { ...app, installed: true, installSize: result.install_size, requiresRepair: result.needs_repair }
+          : app
+      );
+    });
+
+    return result;
+  }
+
+  // Progress is emitted as 'move_progress', calling it again resumes an interrupted move
+  static moveInstallation(appId: string, newBasePath: string) {
+    return Legendary.invokeTyped<MoveResult>('move_installation', { appId, newBasePath });
+  }
+
+  static cancelMove(appId: string) {
+    return Legendary.invokeTyped<boolean>('cancel_move_installation', { appId });
+  }
+
+  // App ids of interrupted moves, mapped to their destination
+  static getPendingMoves() {
+    return invoke<Record<string, string>>('get_pending_moves');
+  }
+
+  static getAppRunner(appId: string) {
+    return invoke<RunnerConfig>('get_app_runner', { appId });
+  }
+
+  static setAppRunner(appId: string, runner: RunnerConfig) {
+    return invoke<void>('set_app_runner', { appId, runner });
+  }
+
+  static syncSaves(appId: string, direction: SaveSyncDirection = 'auto', force = false) {
+    return Legendary.invokeTyped<SaveSyncResult>('sync_saves', { appId, direction, force });
+  }
+
+  static getAutoSaveSync(appId: string) {
+    return invoke<boolean>('get_auto_save_sync', { appId });
+  }
+
+  static setAutoSaveSync(appId: string, enabled: boolean) {
+    return invoke<void>('set_auto_save_sync', { appId, enabled });
+  }
+
   static async uninstall(appId: string) {
-    const data = await Legendary.execute(['uninstall', appId, '-y']);
+    const data = await Legendary.execute({ operation: 'uninstall', app_id: appId });
 
     ownedApps.update(current => {
       return current.map(app =>
@@ -183,7 +379,7 @@ export default class Legendary {
     await Legendary.syncEGL();
     const installedList = await Legendary.getInstalledList();
 
-    ownedApps.set(list.stdout
+    ownedApps.set(list
       .filter(app => app.metadata.entitlementType === 'EXECUTABLE')
       .map(app => {
         const images = app.metadata.keyImages.reduce<Record<string, string>>((acc, image) => {
@@ -191,7 +387,7 @@ export default class Legendary {
           return acc;
         }, {});
 
-        const installed = installedList.stdout.find(installed => installed.app_name === app.app_name);
+        const installed = installedList.find(installed => installed.app_name === app.app_name);
 
         return {
           id: app.app_name,
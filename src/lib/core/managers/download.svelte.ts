@@ -1,27 +1,57 @@
 import { downloaderStorage } from '$lib/core/data-storage';
 import NotificationManager from '$lib/core/managers/notification';
 import { ownedApps } from '$lib/stores';
-import Legendary, { type StreamEvent } from '$lib/core/legendary';
-import { t } from '$lib/utils/util';
-import type { queueItemSchema } from '$lib/validations/settings';
-import type { ParsedApp } from '$types/legendary';
+import Legendary, { type StreamProgress } from '$lib/core/legendary';
+import { bytesToSize, t } from '$lib/utils/util';
+import type { LegendaryFailure, ParsedApp } from '$types/legendary';
 import { invoke } from '@tauri-apps/api/core';
-import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import { listen } from '@tauri-apps/api/event';
 import { toast } from 'svelte-sonner';
 import { get } from 'svelte/store';
-import type { z } from 'zod';
 
 type DownloadType = 'install' | 'update' | 'repair';
-type QueueItem = z.infer<typeof queueItemSchema>;
-type DownloadCallbacks = Partial<{
-  onProgress: (progress: Partial<DownloadProgress>) => void;
-  onComplete: (success: boolean, code?: number) => void;
-  onError: (error: string) => void;
-}>;
+
+export type QueueItem = {
+  status: 'queued' | 'downloading' | 'completed' | 'failed' | 'paused';
+  item: ParsedApp;
+  operation: DownloadType;
+  options: DownloadOptions;
+  added_at: number;
+  started_at: number | null;
+  completed_at: number | null;
+  total_download_size: number | null;
+};
+
+type DownloadOptions = {
+  base_path: string;
+  no_https: boolean;
+  install_tags?: string[];
+  dlc_ids?: string[];
+};
+
+type DownloadQueueState = {
+  items: QueueItem[];
+  downloading_app_id: string | null;
+  progress: StreamProgress | null;
+  low_disk_space: boolean;
+};
+
+type DiskSpaceEvent = {
+  app_id: string;
+  path: string;
+  available: number;
+  threshold: number;
+};
+
+type DownloadFinishedEvent = {
+  item: ParsedApp;
+  operation: DownloadType;
+  success: boolean;
+  reason: LegendaryFailure | null;
+};
 
 export type DownloadProgress = {
   actualDownloadSize: number;
-  currentDownloadSize: number;
   percent: number;
   etaMs: number;
   downloaded: number;
@@ -29,376 +59,187 @@ export type DownloadProgress = {
   diskWriteSpeed: number;
 };
 
+/**
+ * The queue itself lives in the Rust backend, this only mirrors its state for the UI.
+ */
 class DownloadManager {
   downloadingAppId = $state<string | null>(null);
   progress = $state<Partial<DownloadProgress>>({});
   queue = $state<QueueItem[]>([]);
-
-  private activeDownload: {
-    streamId: string;
-    unlisten: UnlistenFn;
-    callbacks: DownloadCallbacks;
-    cancelled?: boolean;
-    paused?: boolean;
-  } | null = null;
+  lowDiskSpace = $state(false);
 
   async init() {
-    const downloaderSettings = get(downloaderStorage);
-    const accountId = await Legendary.getAccount();
-    const queue = accountId ? downloaderSettings.queue?.[accountId] : null;
-
-    if (!downloaderSettings.queue || !accountId || !queue?.length) {
-      return;
-    }
+    await listen<DownloadQueueState>('download_queue_changed', (event) => {
+      this.applyState(event.payload);
+    });
 
-    for (const item of queue) {
-      if (item.status === 'downloading') {
-        item.status = 'paused';
-      }
-    }
+    await listen<DownloadFinishedEvent>('download_finished', (event) => {
+      this.handleFinished(event.payload);
+    });
 
-    this.queue = downloaderSettings.queue[accountId];
-    await this.processQueue(true);
-  }
+    await listen<DiskSpaceEvent>('low_disk_space', (event) => {
+      this.handleLowDiskSpace(event.payload);
+    });
 
-  async addToQueue(app: ParsedApp) {
-    const existingItem = this.queue.find(({ item }) => item.id === app.id);
+    await listen<DiskSpaceEvent>('disk_space_recovered', (event) => {
+      this.handleDiskSpaceRecovered(event.payload);
+    });
 
-    if (existingItem && ['queued', 'downloading', 'paused'].includes(existingItem.status)) {
-      throw new Error('App is already in the download queue');
-    }
+    this.applyState(await this.invokeQueue('get_download_queue'));
+    await this.migrateLegacyQueue();
+  }
 
-    this.queue = [
-      // To remove queue items with completed or failed status
-      ...this.queue.filter(({ item }) => item.id !== app.id),
-      {
-        status: 'queued',
-        item: app,
-        addedAt: Date.now()
-      }
-    ];
+  // Without `installTags` the tags saved for the app are used
+  async addToQueue(app: ParsedApp, installTags?: string[], dlcIds?: string[]) {
+    const settings = get(downloaderStorage);
 
-    await this.saveQueueToFile();
-    return this.processQueue();
+    await this.invokeQueue('enqueue_download', {
+      item: app,
+      options: {
+        base_path: settings.downloadPath!,
+        no_https: !!settings.noHTTPS,
+        install_tags: installTags,
+        dlc_ids: dlcIds
+      } satisfies DownloadOptions
+    });
   }
 
   async removeFromQueue(appId: string) {
-    if (this.downloadingAppId === appId) {
-      await this.cancelDownload();
-    }
-
-    this.queue = this.queue.filter(({ item }) => item.id !== appId);
-    await this.saveQueueToFile();
+    await this.invokeQueue('dequeue_download', { appId });
   }
 
   async moveQueueItem(appId: string, direction: 'up' | 'down') {
-    const queueIndexes = this.queue
-      .map((item, index) => ({ item, index }))
-      .filter(({ item }) => item.status === 'queued')
-      .map(({ index }) => index);
-
-    const currentQueuePosition = queueIndexes.findIndex(i => this.queue[i].item.id === appId);
-    if (currentQueuePosition === -1) return;
-
-    const newQueuePosition = direction === 'up'
-      ? currentQueuePosition - 1
-      : currentQueuePosition + 1;
-
-    if (newQueuePosition < 0 || newQueuePosition >= queueIndexes.length) {
-      return;
-    }
-
-    const currentIndex = queueIndexes[currentQueuePosition];
-    const targetIndex = queueIndexes[newQueuePosition];
-
-    [this.queue[currentIndex], this.queue[targetIndex]] = [this.queue[targetIndex], this.queue[currentIndex]];
-
-    await this.saveQueueToFile();
+    await this.invokeQueue('move_download', { appId, direction });
   }
 
   isInQueue(appId: string): boolean {
     return this.queue.some(({ item, status }) => item.id === appId && ['queued', 'downloading', 'paused'].includes(status));
   }
 
-  async processQueue(processPaused = false) {
-    const pausedItem = this.queue.find(({ status }) => status === 'paused');
-
-    let item: QueueItem | undefined;
-    if (pausedItem) {
-      item = processPaused ? pausedItem : undefined;
-    } else {
-      item = this.queue.find(({ status }) => status === 'queued');
-    }
-
-    if (!item || (this.downloadingAppId && item.status !== 'paused')) return;
-
-    const app = item.item;
-    const type: DownloadType = app.requiresRepair ? 'repair' : app.hasUpdate ? 'update' : 'install';
-
-    this.downloadingAppId = app.id;
-
-    if (item.status !== 'paused') {
-      this.progress = {
-        actualDownloadSize: 0,
-        currentDownloadSize: 0,
-        percent: 0,
-        etaMs: 0,
-        downloaded: 0,
-        downloadSpeed: 0,
-        diskWriteSpeed: 0
-      };
-    }
-
-    item.startedAt = Date.now();
-    await this.setItemStatus(item, 'downloading');
-
-    try {
-      await this.startInstallation(app, {
-        onProgress: (progress: Partial<DownloadProgress>) => {
-          this.progress = {
-            ...this.progress,
-            ...progress
-          };
-        },
-        onComplete: async (success) => {
-          const downloaderSettings = get(downloaderStorage);
-
-          if (success) {
-            app.installed = true;
-            app.hasUpdate = false;
-            app.requiresRepair = false;
-
-            item.completedAt = Date.now();
-
-            const notificationMessage = get(t)(
-              type === 'repair' ? 'library.app.repaired' : type === 'update' ? 'library.app.updated' : 'library.app.installed',
-              { name: app.title }
-            );
-
-            toast.success(notificationMessage);
-
-            if (downloaderSettings.sendNotifications) {
-              NotificationManager.sendNotification(notificationMessage).catch(console.error);
-            }
-
-            ownedApps.update((apps) => {
-              const appIndex = apps.findIndex(x => x.id === app.id);
-              if (appIndex !== -1) {
-                apps[appIndex] = app;
-              } else {
-                apps.push(app);
-              }
-
-              return apps;
-            });
-
-            await this.setItemStatus(item, 'completed');
-          } else if (!this.activeDownload?.cancelled && !this.activeDownload?.paused) {
-            await this.handleDownloadError(item, type);
-          }
-
-          if (!this.activeDownload?.paused) {
-            this.cleanupActiveDownload();
-          }
-        },
-        onError: async (error) => {
-          await this.handleDownloadError(item, type, error);
-          this.cleanupActiveDownload();
-        }
-      });
-    } catch (error) {
-      await this.handleDownloadError(item, type, error);
-      this.cleanupActiveDownload();
-    }
-  }
-
   async cancelDownload() {
-    if (!this.activeDownload) return;
-
-    this.activeDownload.cancelled = true;
-
-    // If it was paused, the stream is already stopped so we just clean up
-    if (this.activeDownload.paused) {
-      this.queue = this.queue.filter(q => q.item.id !== this.downloadingAppId);
-      this.cleanupActiveDownload();
-    } else {
-      await invoke<boolean>('stop_legendary_stream', {
-        streamId: this.activeDownload.streamId,
-        forceKillAll: true
-      });
-    }
+    if (!this.downloadingAppId) return;
+
+    await this.removeFromQueue(this.downloadingAppId);
   }
 
   async pauseDownload() {
-    const activeDownload = this.activeDownload;
-    if (!activeDownload || activeDownload.paused) return;
-
-    activeDownload.paused = true;
-
-    await invoke<boolean>('stop_legendary_stream', {
-      streamId: activeDownload.streamId,
-      forceKillAll: true
-    });
-
-    activeDownload.unlisten();
-    activeDownload.streamId = '';
-
-    const item = this.queue.find(({ item }) => item.id === this.downloadingAppId);
-    if (item) {
-      await this.setItemStatus(item, 'paused');
-    }
+    this.applyState(await invoke<DownloadQueueState>('pause_download'));
   }
 
   async resumeDownload() {
-    await this.processQueue(true);
+    this.applyState(await invoke<DownloadQueueState>('resume_download'));
   }
 
   async clearCompleted() {
-    this.queue = this.queue.filter(({ status }) => status !== 'completed' && status !== 'failed');
-    await this.saveQueueToFile();
+    await this.invokeQueue('clear_finished_downloads');
   }
 
-  private async handleDownloadError(item: QueueItem, type: DownloadType, error?: unknown) {
-    if (error) console.error(error);
+  private async invokeQueue(command: string, args: Record<string, unknown> = {}) {
+    const state = await invoke<DownloadQueueState>(command, {
+      configPath: await Legendary.getConfigPath(),
+      ...args
+    });
+
+    this.applyState(state);
+    return state;
+  }
+
+  private applyState(state: DownloadQueueState) {
+    this.queue = state.items;
+    this.downloadingAppId = state.downloading_app_id;
+    this.lowDiskSpace = state.low_disk_space;
+
+    const progress = state.progress;
+    this.progress = progress ? {
+      actualDownloadSize: progress.total,
+      downloaded: progress.downloaded,
+      percent: progress.percent,
+      etaMs: progress.eta_ms,
+      downloadSpeed: progress.download_speed,
+      diskWriteSpeed: progress.disk_write_speed
+    } : {};
+  }
+
+  private handleFinished({ item: app, operation, success, reason }: DownloadFinishedEvent) {
+    if (!success) {
+      toast.error(get(t)(
+        operation === 'repair' ? 'library.app.failedToRepair' : operation === 'update' ? 'library.app.failedToUpdate' : 'library.app.failedToInstall',
+        { name: app.title }
+      ), {
+        description: reason ? Legendary.describeFailure(reason) || undefined : undefined
+      });
+
+      return;
+    }
 
-    const app = item.item;
-    const errorMessage = get(t)(
-      type === 'repair' ? 'library.app.failedToRepair' : type === 'update' ? 'library.app.failedToUpdate' : 'library.app.failedToInstall',
+    const notificationMessage = get(t)(
+      operation === 'repair' ? 'library.app.repaired' : operation === 'update' ? 'library.app.updated' : 'library.app.installed',
       { name: app.title }
     );
 
-    toast.error(errorMessage);
+    toast.success(notificationMessage);
 
-    item.completedAt = Date.now();
-    await this.setItemStatus(item, 'failed');
-  }
-
-  private async startInstallation(app: ParsedApp, callbacks: DownloadCallbacks = {}) {
-    const settings = get(downloaderStorage);
-    const streamId = `install_${app.id}_${Date.now()}`;
-    const args = [app.requiresRepair ? 'repair' : 'install', app.id, '-y', '--skip-sdl', '--skip-dlcs', '--base-path', settings.downloadPath!];
-
-    if (settings.noHTTPS) {
-      args.push('--no-https');
+    if (get(downloaderStorage).sendNotifications) {
+      NotificationManager.sendNotification(notificationMessage).catch(console.error);
     }
 
-    const unlisten = await listen<StreamEvent>(`legendary_stream:${streamId}`, (event) => {
-      const payload = event.payload;
-
-      switch (payload.event_type) {
-        case 'stdout':
-        case 'stderr': {
-          const result = this.parseDownloadOutput(payload.data);
-          if (Object.keys(result).length) {
-            callbacks.onProgress?.(result);
-          }
-          break;
-        }
-
-        case 'terminated': {
-          callbacks.onComplete?.(payload.code === 0, payload.code);
-          break;
-        }
-
-        case 'error': {
-          callbacks.onError?.(payload.data);
-          break;
-        }
+    ownedApps.update((apps) => {
+      const appIndex = apps.findIndex(x => x.id === app.id);
+      if (appIndex !== -1) {
+        apps[appIndex] = app;
+      } else {
+        apps.push(app);
       }
-    });
 
-    await invoke('start_legendary_stream', {
-      configPath: await Legendary.getConfigPath(),
-      args,
-      streamId
+      return apps;
     });
-
-    this.activeDownload = {
-      streamId,
-      unlisten,
-      callbacks
-    };
-
-    return streamId;
-  }
-
-  private setItemStatus(item: QueueItem, status: QueueItem['status']) {
-    item.status = status;
-    this.queue = [...this.queue];
-
-    return this.saveQueueToFile();
   }
 
-  private async saveQueueToFile() {
-    const accountId = (await Legendary.getAccount())!;
-    return downloaderStorage.update((settings) => {
-      settings.queue = {
-        ...settings.queue,
-        [accountId]: $state.snapshot(this.queue)
-      };
+  private handleLowDiskSpace({ app_id, available }: DiskSpaceEvent) {
+    const title = this.queue.find(({ item }) => item.id === app_id)?.item.title || app_id;
+    const message = get(t)('downloads.lowDiskSpace.paused', { name: title, available: bytesToSize(available) });
 
-      return settings;
-    });
-  }
+    toast.warning(message);
 
-  private cleanupActiveDownload() {
-    if (!this.activeDownload?.paused) {
-      this.activeDownload?.unlisten();
+    if (get(downloaderStorage).sendNotifications) {
+      NotificationManager.sendNotification(message).catch(console.error);
     }
-
-    this.activeDownload = null;
-    this.downloadingAppId = null;
-    this.progress = {};
-
-    this.processQueue().catch(console.error);
   }
 
-  private parseDownloadOutput(output: string) {
-    const MiBtoBytes = (mib: string) => Number.parseFloat(mib) * 1024 * 1024;
-    const result: Partial<DownloadProgress> = {};
-
-    let match = output.match(/Download size: ([\d.]+) MiB/);
-    if (match) {
-      result.currentDownloadSize = MiBtoBytes(match[1]);
+  private handleDiskSpaceRecovered({ app_id }: DiskSpaceEvent) {
+    const title = this.queue.find(({ item }) => item.id === app_id)?.item.title || app_id;
 
-      if (!this.progress.actualDownloadSize) {
-        result.actualDownloadSize = result.currentDownloadSize;
+    toast.info(get(t)('downloads.lowDiskSpace.recovered', { name: title }), {
+      duration: Infinity,
+      action: {
+        label: get(t)('downloads.lowDiskSpace.resume'),
+        onClick: () => this.resumeDownload().catch(console.error)
       }
+    });
+  }
 
-      return result;
-    }
-
-    match = output.match(/ETA: (\d{2}:\d{2}:\d{2})/);
-    if (match) {
-      const [h, m, s] = match[1].split(':').map(Number);
-      result.etaMs = ((h * 3600) + (m * 60) + s) * 1000;
-      return result;
-    }
-
-    match = output.match(/Downloaded: ([\d.]+) MiB/);
-    if (match) {
-      const downloaded = MiBtoBytes(match[1]);
-      const totalDownloaded = downloaded + this.progress.actualDownloadSize! - this.progress.currentDownloadSize!;
+  // Queues used to be saved in the downloader settings, move any unfinished items to the backend
+  async migrateLegacyQueue() {
+    const legacyQueue = get(downloaderStorage).queue;
+    if (!legacyQueue) return;
 
-      result.percent = totalDownloaded / this.progress.actualDownloadSize! * 100;
-      result.downloaded = totalDownloaded;
-      return result;
-    }
+    // Items are queued for the logged in account, the others are migrated when they log in
+    const accountId = await Legendary.getAccount();
+    if (!accountId || !legacyQueue[accountId]) return;
 
-    match = output.match(/Download\s+- ([\d.]+) MiB\/s \(raw\)/);
-    if (match) {
-      result.downloadSpeed = MiBtoBytes(match[1]);
-      return result;
+    for (const { item, status } of legacyQueue[accountId]) {
+      if (['queued', 'downloading', 'paused'].includes(status)) {
+        await this.addToQueue(item).catch(console.error);
+      }
     }
 
-    match = output.match(/Disk\s+- ([\d.]+) MiB\/s \(write\)/);
-    if (match) {
-      result.diskWriteSpeed = MiBtoBytes(match[1]);
-      return result;
-    }
+    downloaderStorage.update((settings) => {
+      delete settings.queue?.[accountId];
+      if (settings.queue && !Object.keys(settings.queue).length) delete settings.queue;
 
-    return {};
+      return settings;
+    });
   }
 }
 
-export default new DownloadManager();
\ No newline at end of file
+export default new DownloadManager();
@@ -1,7 +1,12 @@
+import type { LegendaryFailure } from '$types/legendary';
+
 export default class LegendaryError extends Error {
-  constructor(message: string) {
+  reason?: LegendaryFailure;
+
+  constructor(message: string, reason?: LegendaryFailure) {
     super();
     this.name = 'LegendaryError';
     this.message = message.trim();
+    this.reason = reason;
   }
 }
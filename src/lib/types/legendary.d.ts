@@ -135,7 +135,53 @@ export type LegendaryInstalledList = Array<{
 
 export type ParsedApp = z.infer<typeof parsedAppSchema>;
 
+export type LegendaryFailure =
+  | 'not_logged_in'
+  | 'token_expired'
+  | 'insufficient_disk_space'
+  | 'network'
+  | 'not_owned'
+  | 'install_path_conflict'
+  | 'needs_repair'
+  | 'unknown';
+
+export type LegendaryCommandError =
+  | { kind: 'process'; message: string; }
+  | { kind: 'failed'; code: number | null; stderr: string; reason: LegendaryFailure; }
+  | { kind: 'invalid_output'; command: string; message: string; }
+  | { kind: 'timed_out'; timeout_ms: number; }
+  | { kind: 'cancelled'; }
+  | { kind: 'invalid_operation'; message: string; };
+
+export type LegendaryInstallOptions = {
+  base_path?: string;
+  no_https?: boolean;
+  skip_sdl?: boolean;
+  skip_dlcs?: boolean;
+  install_tags?: string[];
+};
+
+export type LegendaryOperation =
+  | { operation: 'install' | 'update' | 'repair'; app_id: string; options?: LegendaryInstallOptions; }
+  | { operation: 'verify'; app_id: string; }
+  | { operation: 'uninstall'; app_id: string; keep_files?: boolean; }
+  | { operation: 'import'; app_id: string; path: string; }
+  | { operation: 'move'; app_id: string; new_base_path: string; skip_move?: boolean; }
+  | { operation: 'launch'; app_id: string; }
+  | { operation: 'auth'; action: { login: { exchange_code: string; }; } | 'logout'; }
+  | { operation: 'egl_sync'; }
+  | { operation: 'sync_saves'; app_id: string; direction?: SaveSyncDirection; force?: boolean; interactive?: boolean; }
+  | { operation: 'info' | 'list_files'; app_id: string; }
+  | { operation: 'list' | 'list_installed' | 'status'; };
+
+export type LegendaryRunOptions = {
+  timeout_ms?: number;
+  request_id?: string;
+  log?: boolean;
+};
+
 export type LegendaryLaunchData = {
+  game_id: string;
   game_parameters: Array<string>;
   game_executable: string;
   game_directory: string;
@@ -146,4 +192,118 @@ export type LegendaryLaunchData = {
   environment: Record<string, string>;
   pre_launch_command: string;
   pre_launch_wait: boolean;
-};
\ No newline at end of file
+};
+
+export type RunnerKind = 'native' | 'wine' | 'proton';
+
+export type RunnerConfig = {
+  kind: RunnerKind;
+  binary_path: string;
+  prefix_path: string;
+  environment: Record<string, string>;
+  wrappers: string[];
+};
+
+export type PreflightProblem =
+  | { kind: 'already_installed'; install_path: string; }
+  | { kind: 'manifest_unavailable'; }
+  | { kind: 'invalid_path'; }
+  | { kind: 'insufficient_space'; required: number; available: number; }
+  | { kind: 'below_disk_space_threshold'; left: number; threshold: number; }
+  | { kind: 'not_writable'; message: string; }
+  | { kind: 'disk_space_unavailable'; message: string; }
+  | { kind: 'target_not_empty'; path: string; }
+  | { kind: 'dlc_not_owned'; app_id: string; }
+  | { kind: 'dlc_size_unknown'; app_id: string; };
+
+export type PreflightReport = {
+  app_id: string;
+  install_path: string | null;
+  download_size: number;
+  disk_size: number;
+  dlc_download_size: number;
+  dlc_disk_size: number;
+  dlcs: DlcInfo[];
+  available_space: number | null;
+  can_install: boolean;
+  problems: PreflightProblem[];
+  warnings: PreflightProblem[];
+};
+
+export type Volume = {
+  mount_point: string;
+  label: string;
+  file_system: string;
+  total: number;
+  available: number;
+  removable: boolean;
+  read_only: boolean;
+};
+
+export type ImportResult = {
+  app_id: string;
+  install_path: string;
+  version: string;
+  install_size: number;
+  needs_repair: boolean;
+};
+
+export type MoveStage = 'copying' | 'registering' | 'verifying' | 'removing_source' | 'completed' | 'rolling_back' | 'rolled_back' | 'failed';
+
+export type MoveProgress = {
+  app_id: string;
+  stage: MoveStage;
+  copied: number;
+  total: number;
+};
+
+export type MoveResult = {
+  app_id: string;
+  install_path: string;
+  source_removed: boolean;
+};
+
+export type SaveSyncDirection = 'upload' | 'download' | 'auto';
+
+export type SaveSyncOutcome = 'uploaded' | 'downloaded' | 'skipped' | 'conflict';
+
+export type SaveSyncResult = {
+  app_id: string;
+  outcome: SaveSyncOutcome;
+  save_path: string | null;
+  files: Array<{ path: string; outcome: SaveSyncOutcome; }>;
+};
+
+export type SaveSyncEvent = {
+  app_id: string;
+  trigger: 'before_launch' | 'after_exit';
+  result: SaveSyncResult | null;
+  error: string | null;
+};
+
+export type DlcInfo = {
+  app_id: string;
+  title: string;
+  installed: boolean;
+  download_size: number | null;
+  disk_size: number | null;
+};
+
+export type DlcResult = {
+  app_id: string;
+  success: boolean;
+  reason: LegendaryFailure | null;
+};
+
+export type InstallTag = {
+  tag: string;
+  download_size: number;
+  disk_size: number;
+  file_count: number;
+};
+
+export type InstallTagInfo = {
+  app_id: string;
+  tags: InstallTag[];
+  selected: string[] | null;
+};
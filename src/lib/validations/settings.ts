@@ -106,5 +106,6 @@ export const downloaderSettingsSchema = z.object({
   favoriteApps: z.array(z.string()),
   hiddenApps: z.array(z.string()),
   perAppAutoUpdate: z.record(z.string(), z.boolean()),
+  // Only read to move old queues to the backend
   queue: z.record(z.string(), z.array(queueItemSchema))
 }).partial();
\ No newline at end of file